@@ -1,10 +1,33 @@
+use proc_macro2::Span;
+use quote::ToTokens;
 use syn::parse::{Error, Parse, ParseStream, Result};
-use syn::{LitInt, Path, Token};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Expr, Ident, LitInt, LitStr, Meta, Path, Token};
 
 pub enum Args {
     None,
-    Path(Path),
-    PathPos(Path, usize),
+    Element(Box<ElementArgs>),
+}
+
+// `SLICE`, `SLICE, <pos>` for compatibility, or `SLICE` followed by, or just,
+// `key = value` pairs:
+//
+//   slices = [A, B]   add the element to each slice
+//   pos = 10          order within the slice, lower first
+//   name = "json"     name reported at runtime
+//   after = ["a"]     names of the elements `init::run` runs this one after
+//   before = ["b"]    names of the elements `init::run` runs this one before
+//   when = <cfg>      only add the element when the predicate holds
+//   value = <expr>    the object an `impl Trait for Type` element adds to a
+//                     slice of `&'static dyn Trait`
+pub struct ElementArgs {
+    pub slices: Vec<Path>,
+    pub pos: Option<usize>,
+    pub name: Option<LitStr>,
+    pub after: Vec<LitStr>,
+    pub before: Vec<LitStr>,
+    pub when: Option<Meta>,
+    pub value: Option<Expr>,
 }
 
 impl Parse for Args {
@@ -12,16 +35,108 @@ impl Parse for Args {
         if input.is_empty() {
             return Ok(Args::None);
         }
-        let path: Path = input.parse()?;
-        if input.is_empty() {
-            return Ok(Args::Path(path));
+        let mut args = ElementArgs {
+            slices: Vec::new(),
+            pos: None,
+            name: None,
+            after: Vec::new(),
+            before: Vec::new(),
+            when: None,
+            value: None,
+        };
+        let mut seen = Vec::<String>::new();
+
+        if !(input.peek(Ident) && input.peek2(Token![=])) {
+            args.slices.push(input.parse()?);
+            if input.is_empty() {
+                return Ok(Args::Element(Box::new(args)));
+            }
+            input.parse::<Token![,]>()?;
+            if input.peek(LitInt) {
+                args.pos = Some(parse_pos(input)?);
+                seen.push("pos".to_owned());
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+        }
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let key_str = key.to_string();
+            if seen.contains(&key_str) {
+                return Err(Error::new(key.span(), format!("duplicate `{}` argument", key)));
+            }
+            match key_str.as_str() {
+                "slices" => {
+                    if !args.slices.is_empty() {
+                        return Err(Error::new(
+                            key.span(),
+                            "the slice is already given as the first argument",
+                        ));
+                    }
+                    let content;
+                    let brackets = bracketed!(content in input);
+                    let slices = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+                    if slices.is_empty() {
+                        return Err(Error::new(brackets.span.join(), "expected at least one slice"));
+                    }
+                    for slice in slices {
+                        let tokens = slice.to_token_stream().to_string();
+                        if args.slices.iter().any(|other| other.to_token_stream().to_string() == tokens) {
+                            return Err(Error::new_spanned(slice, "slice is listed twice"));
+                        }
+                        args.slices.push(slice);
+                    }
+                }
+                "pos" => args.pos = Some(parse_pos(input)?),
+                "name" => args.name = Some(input.parse()?),
+                "after" => args.after = parse_names(input)?,
+                "before" => args.before = parse_names(input)?,
+                "when" => args.when = Some(input.parse()?),
+                "value" => args.value = Some(input.parse()?),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        format!(
+                            "unknown distributed_fn_slice argument `{}`, expected `slices`, `pos`, `name`, `after`, `before`, `when` or `value`",
+                            key,
+                        ),
+                    ));
+                }
+            }
+            seen.push(key_str);
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
         }
-        input.parse::<Token![,]>()?;
-        let lit: LitInt = input.parse()?;
-        let pos: usize = lit.base10_parse()?;
-        if pos > 9999 {
-            return Err(Error::new(lit.span(), "maximum 9999 is supported"));
+
+        if args.slices.is_empty() {
+            return Err(Error::new(
+                Span::call_site(),
+                "missing the slice to add the element to, e.g. `#[distributed_fn_slice(SLICE)]`",
+            ));
         }
-        Ok(Args::PathPos(path, pos))
+        Ok(Args::Element(Box::new(args)))
+    }
+}
+
+// ["a", "b"]
+fn parse_names(input: ParseStream) -> Result<Vec<LitStr>> {
+    let content;
+    bracketed!(content in input);
+    let names = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+    Ok(names.into_iter().collect())
+}
+
+// `usize::MAX` stands for no position in the metadata records.
+fn parse_pos(input: ParseStream) -> Result<usize> {
+    let lit: LitInt = input.parse()?;
+    let pos = lit.base10_parse()?;
+    if pos == usize::MAX {
+        return Err(Error::new(lit.span(), "`pos` must be less than usize::MAX"));
     }
+    Ok(pos)
 }
@@ -0,0 +1,125 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Error, Result};
+use syn::{BareFnArg, ItemStatic, LitStr, ReturnType, Type};
+
+// #[c_registry("handlers")] above the #[distributed_fn_slice] of a slice adds
+// an element to the runtime's slice of registries, which exports the slice
+// under the name through the C API of `generic_linkme::c_api`. It carries the
+// C types of the element signature, for the header.
+pub fn expand(name: LitStr, input: ItemStatic) -> TokenStream {
+    match do_expand(name, input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(name: LitStr, item: ItemStatic) -> Result<TokenStream> {
+    let value = name.value();
+    let identifier = value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && value.starts_with(|c: char| !c.is_ascii_digit());
+    if !identifier {
+        return Err(Error::new_spanned(&name, "registry names are C identifiers"));
+    }
+    let declared = item.attrs.iter().any(|attr| {
+        matches!(attr.path().segments.last(), Some(segment) if segment.ident == "distributed_fn_slice")
+    });
+    let element_type = match &*item.ty {
+        Type::Slice(ty) if declared => match &*ty.elem {
+            Type::BareFn(ty) => ty,
+            _ => return Err(Error::new_spanned(&ty.elem, "#[c_registry] slices have function pointer elements")),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &item,
+                "#[c_registry] goes above the #[distributed_fn_slice] that declares the slice",
+            ));
+        }
+    };
+    if let Some(variadic) = &element_type.variadic {
+        return Err(Error::new_spanned(variadic, "#[c_registry] elements cannot be variadic"));
+    }
+    let args = element_type.inputs.iter().map(|arg: &BareFnArg| c_type(&arg.ty)).collect::<Result<Vec<_>>>()?;
+    let ret = match &element_type.output {
+        ReturnType::Default => "void".to_owned(),
+        ReturnType::Type(_, ty) => c_type(ty)?,
+    };
+
+    // Only the declaration's own `linkme` attribute, which it keeps.
+    let linkme_path = attr::linkme_path(&mut item.attrs.clone())?;
+    let ident = &item.ident;
+    let element = format_ident!("_generic_linkme_export_{}", ident);
+    let link = mention::link_once(&linkme_path, [&element]);
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice(#linkme_path::__private::GENERIC_LINKME_REGISTRIES)]
+        #[linkme(crate = #linkme_path)]
+        #[allow(non_snake_case)]
+        fn #element() -> #linkme_path::__private::Export {
+            #linkme_path::__private::Export {
+                name: #name,
+                ret: #ret,
+                args: &[#(#args),*],
+                len: || #ident.len(),
+                address: |index| #ident[index] as #linkme_path::__private::usize,
+                element_name: |index| #ident.describe(index).and_then(|entry| entry.name()),
+            }
+        }
+
+        #link
+    })
+}
+
+// The C type of an argument or return type, for the types with one.
+fn c_type(ty: &Type) -> Result<String> {
+    match ty {
+        Type::Paren(ty) => c_type(&ty.elem),
+        Type::Group(ty) => c_type(&ty.elem),
+        Type::Tuple(ty) if ty.elems.is_empty() => Ok("void".to_owned()),
+        Type::Ptr(ptr) => {
+            let pointee = match c_type(&ptr.elem) {
+                Ok(pointee) => pointee,
+                Err(_) => "void".to_owned(),
+            };
+            // A pointer to a pointer qualifies the inner pointer after its `*`.
+            match (ptr.const_token, pointee.ends_with('*')) {
+                (Some(_), false) => Ok(format!("const {} *", pointee)),
+                (Some(_), true) => Ok(format!("{}const *", pointee)),
+                (None, false) => Ok(format!("{} *", pointee)),
+                (None, true) => Ok(format!("{}*", pointee)),
+            }
+        }
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last().unwrap();
+            let c = match segment.ident.to_string().as_str() {
+                "i8" => "int8_t",
+                "i16" => "int16_t",
+                "i32" => "int32_t",
+                "i64" => "int64_t",
+                "u8" => "uint8_t",
+                "u16" => "uint16_t",
+                "u32" => "uint32_t",
+                "u64" => "uint64_t",
+                "isize" => "intptr_t",
+                "usize" => "size_t",
+                "f32" => "float",
+                "f64" => "double",
+                "bool" => "bool",
+                "c_char" => "char",
+                "c_void" => "void",
+                _ => return Err(unsupported(ty)),
+            };
+            Ok(c.to_owned())
+        }
+        _ => Err(unsupported(ty)),
+    }
+}
+
+fn unsupported(ty: &Type) -> Error {
+    Error::new_spanned(
+        ty,
+        "#[c_registry] element signatures are limited to integers, floats, `bool` and raw pointers",
+    )
+}
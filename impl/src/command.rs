@@ -0,0 +1,135 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::{parse_quote, Expr, ExprLit, GenericParam, Ident, ItemFn, Lit, LitStr, Meta, Path, Token};
+
+// `slice = COMMANDS` and `name = "export"` in any order.
+pub struct CommandArgs {
+    slice: Option<Path>,
+    name: Option<LitStr>,
+}
+
+impl Parse for CommandArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut slice = None;
+        let mut name = None;
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let duplicate = match key.to_string().as_str() {
+                "slice" => slice.replace(input.parse()?).is_some(),
+                "name" => name.replace(input.parse()?).is_some(),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        format!("unknown command argument `{}`, expected `slice` or `name`", key),
+                    ));
+                }
+            };
+            if duplicate {
+                return Err(Error::new(key.span(), format!("duplicate `{}` argument", key)));
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(CommandArgs { slice, name })
+    }
+}
+
+// #[command(slice = COMMANDS, name = "export")] adds an element to COMMANDS
+// describing the function as a subcommand, with the name as its `name`. A
+// generic command takes one type parameter and mentions the element for it
+// in its body, so that every instantiation linked into the program becomes a
+// subcommand of its own, named after the type argument.
+pub fn expand(args: CommandArgs, input: ItemFn) -> TokenStream {
+    match do_expand(args, input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(args: CommandArgs, mut item: ItemFn) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let slice = match args.slice {
+        Some(slice) => slice,
+        None => {
+            let msg = "missing the slice to add the command to, e.g. `#[command(slice = COMMANDS)]`";
+            return Err(Error::new(proc_macro2::Span::call_site(), msg));
+        }
+    };
+    let ident = &item.sig.ident;
+    let name = match args.name {
+        Some(name) => name,
+        None => LitStr::new(&ident.to_string().replace('_', "-"), ident.span()),
+    };
+    let help = help(&item);
+
+    let mut params = item.sig.generics.params.iter();
+    let param = match (params.next(), params.next()) {
+        (None, _) => None,
+        (Some(GenericParam::Type(param)), None) => Some(param.ident.clone()),
+        _ => {
+            let msg = "commands take at most one type parameter, which names the subcommand";
+            return Err(Error::new_spanned(&item.sig.generics, msg));
+        }
+    };
+
+    let element = format_ident!("{}_generic_linkme_command", ident);
+    let generics = &item.sig.generics;
+    let where_clause = &item.sig.generics.where_clause;
+    let (turbofish, type_name) = match &param {
+        Some(param) => (
+            quote!(::<#param>),
+            quote!(::core::option::Option::Some(#linkme_path::__private::type_name::<#param>())),
+        ),
+        None => (quote!(), quote!(::core::option::Option::None)),
+    };
+    // Elements are only linked once they are mentioned, a generic one for
+    // each instantiation of the command and otherwise once.
+    let link = match &param {
+        Some(param) => {
+            let mention = mention::mention(&linkme_path, quote!(#element::<#param>));
+            let block = &item.block;
+            item.block = parse_quote!({
+                #mention
+                #block
+            });
+            quote!()
+        }
+        None => mention::link_once(&linkme_path, [&element]),
+    };
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice(#slice, name = #name)]
+        #[linkme(crate = #linkme_path)]
+        fn #element #generics () -> #linkme_path::commands::Command #where_clause {
+            #linkme_path::commands::Command {
+                name: #linkme_path::commands::private_name(#name, #type_name),
+                help: #help,
+                run: #ident #turbofish,
+            }
+        }
+
+        #link
+    })
+}
+
+// The doc comment, without the space that follows `///`.
+fn help(item: &ItemFn) -> String {
+    let mut lines = Vec::new();
+    for attr in &item.attrs {
+        if let Meta::NameValue(meta) = &attr.meta {
+            if let Expr::Lit(ExprLit { lit: Lit::Str(doc), .. }) = &meta.value {
+                if meta.path.is_ident("doc") {
+                    let doc = doc.value();
+                    lines.extend(doc.split('\n').map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end().to_owned()));
+                }
+            }
+        }
+    }
+    lines.join("\n").trim().to_owned()
+}
@@ -1,10 +1,11 @@
-use crate::{attr, linker};
+use crate::{attr, linker, trampoline};
 use proc_macro2::{Span, TokenStream};
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::parse::{Parse, ParseStream, Result};
+use syn::spanned::Spanned;
 use syn::{
-    bracketed, Attribute, Error, GenericArgument, Ident, Lifetime, PathArguments, Token, Type,
-    Visibility, TypeSlice,
+    bracketed, parse_quote, Attribute, Error, GenericArgument, Ident, Lifetime, PathArguments,
+    Token, Type, TypeBareFn, Visibility, TypeSlice,
 };
 
 struct Declaration {
@@ -28,26 +29,18 @@ impl Parse for Declaration {
         }
         let ident: Ident = input.parse()?;
         input.parse::<Token![:]>()?;
-        let mut ty: Type = input.parse()?;
-        let inner_ty: &mut Type = match &mut ty {
-            Type::Slice(TypeSlice { elem, .. }) => &mut *elem,
+        let ty: Type = input.parse()?;
+        let inner_ty: &Type = match &ty {
+            Type::Slice(TypeSlice { elem, .. }) => elem,
             _ => return Err(Error::new_spanned(
                 ty.to_token_stream(),
                 "distributed_fn_slice must be a slice",
             )),
         };
-        let mut fn_ty = match inner_ty {
-            Type::BareFn(fn_ty) => fn_ty,
-            _ => return Err(Error::new_spanned(
-                inner_ty.to_token_stream(),
-                "distributed_fn_slice can only contain bare function pointers",
-            )),
-        };
-        match &fn_ty.abi {
-            None => {
-                fn_ty.abi = Some(syn::parse2(quote! {extern "sysv64"}).unwrap());
-            }
-            Some(abi) => {
+        // Other element types, such as aliases and newtypes, go through the
+        // `FnPointer` trait.
+        if let Type::BareFn(fn_ty) = inner_ty {
+            if let Some(abi) = &fn_ty.abi {
                 let is_c = abi.name.is_none() || abi.name.as_ref().unwrap().to_token_stream().to_string().trim() == "\"sysv64\"";
                 if !is_c {
                     return Err(Error::new_spanned(
@@ -98,6 +91,75 @@ pub fn expand(input: TokenStream) -> TokenStream {
 
     populate_static_lifetimes(&mut ty);
 
+    // An explicit ABI is checked here, elements check their signature through
+    // the static.
+    let mut abi_check = None;
+    let mut object_elem = None;
+    let mut pointer_sized = None;
+    if let Type::Slice(TypeSlice { elem, .. }) = &mut ty {
+        if is_trait_object_ref(elem) {
+            // The elements return the trait objects, which are collected
+            // once, on first use.
+            object_elem = Some(elem.clone());
+        } else if let Type::BareFn(fn_ty) = &**elem {
+            let mut plain = fn_ty.clone();
+            plain.abi = None;
+            let trampoline_ty = trampoline::fn_type(&linkme_path, &plain);
+            if fn_ty.abi.is_none() {
+                **elem = trampoline_ty;
+            } else {
+                abi_check = Some(quote! {
+                    fn __abicheck(element: #fn_ty) -> #trampoline_ty {
+                        element
+                    }
+                });
+            }
+        } else {
+            // Written out or made for trait objects, the element types are
+            // function pointers. Through `FnPointer`, the unsafe impl only
+            // promises one, so the size and alignment are checked here.
+            let elem_ty = elem.clone();
+            let trampoline_ty = quote!(<#elem_ty as #linkme_path::FnPointer>::Trampoline);
+            pointer_sized = Some(quote_spanned! {elem.span()=>
+                const _: () = ::core::assert!(
+                    #linkme_path::__private::mem::size_of::<#trampoline_ty>()
+                        == #linkme_path::__private::mem::size_of::<#linkme_path::__private::usize>()
+                        && #linkme_path::__private::mem::align_of::<#trampoline_ty>()
+                            == #linkme_path::__private::mem::align_of::<#linkme_path::__private::usize>(),
+                    "the FnPointer::Trampoline of a distributed_fn_slice element must be a function pointer",
+                );
+            });
+            **elem = Type::Verbatim(trampoline_ty);
+        }
+    }
+    // The element type of a slice of trait objects is declared under the
+    // name of the slice in the type namespace, so that the impls added to it
+    // name it through the same path as the slice, also when it is imported
+    // or renamed.
+    let cfg_attrs = attrs.iter().filter(|attr| attr.path().is_ident("cfg"));
+    let object_alias = object_elem.as_ref().map(|object| quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #(#cfg_attrs)*
+        #vis type #ident = #object;
+    });
+    let object = match &object_elem {
+        Some(object) => {
+            let plain: TypeBareFn = parse_quote!(fn() -> #object);
+            let trampoline_ty = trampoline::fn_type(&linkme_path, &plain);
+            quote! {
+                ::core::option::Option::Some({
+                    unsafe fn object(address: #linkme_path::__private::usize) -> #object {
+                        let element: #trampoline_ty = #linkme_path::__private::mem::transmute(address);
+                        element()
+                    }
+                    object
+                })
+            }
+        }
+        None => quote!(::core::option::Option::None),
+    };
+
     let used = if cfg!(feature = "used_linker") {
         quote!(#[used(linker)])
     } else {
@@ -110,6 +172,10 @@ pub fn expand(input: TokenStream) -> TokenStream {
     let linux_dupcheck = linux_section.replacen("generic_linkme", "generic_linkm2", 1);
     let linux_dupcheck_start = linux_section_start.replacen("generic_linkme", "generic_linkm2", 1);
     let linux_dupcheck_stop = linux_section_stop.replacen("generic_linkme", "generic_linkm2", 1);
+    let linux_table = linux_section.replacen("generic_linkme", "generic_linkme_tbl", 1);
+    let linux_metadata = linux_section.replacen("generic_linkme", "generic_linkm3", 1);
+    let linux_metadata_start = linux_section_start.replacen("generic_linkme", "generic_linkm3", 1);
+    let linux_metadata_stop = linux_section_stop.replacen("generic_linkme", "generic_linkm3", 1);
 
     let macho_section = linker::macho::section(&ident);
     let macho_section_start = linker::macho::section_start(&ident);
@@ -117,6 +183,9 @@ pub fn expand(input: TokenStream) -> TokenStream {
     let macho_dupcheck = macho_section.replacen("glinkm", "glink2", 1);
     let macho_dupcheck_start = macho_section_start.replacen("glinkm", "glink2", 1);
     let macho_dupcheck_stop = macho_section_stop.replacen("glinkm", "glink2", 1);
+    let macho_metadata = macho_section.replacen("glinkm", "glink3", 1);
+    let macho_metadata_start = macho_section_start.replacen("glinkm", "glink3", 1);
+    let macho_metadata_stop = macho_section_stop.replacen("glinkm", "glink3", 1);
 
     let windows_section = linker::windows::section(&ident);
     let windows_section_start = linker::windows::section_start(&ident);
@@ -124,6 +193,9 @@ pub fn expand(input: TokenStream) -> TokenStream {
     let windows_dupcheck = windows_section.replacen("glinkme", "glinkm2", 1);
     let windows_dupcheck_start = windows_section_start.replacen("glinkme", "glinkm2", 1);
     let windows_dupcheck_stop = windows_section_stop.replacen("glinkme", "glinkm2", 1);
+    let windows_metadata = windows_section.replacen("glinkme", "glinkm3", 1);
+    let windows_metadata_start = windows_section_start.replacen("glinkme", "glinkm3", 1);
+    let windows_metadata_stop = windows_section_stop.replacen("glinkme", "glinkm3", 1);
 
     let illumos_section = linker::illumos::section(&ident);
     let illumos_section_start = linker::illumos::section_start(&ident);
@@ -131,6 +203,10 @@ pub fn expand(input: TokenStream) -> TokenStream {
     let illumos_dupcheck = illumos_section.replacen("generic_linkme", "generic_linkm2", 1);
     let illumos_dupcheck_start = illumos_section_start.replacen("generic_linkme", "generic_linkm2", 1);
     let illumos_dupcheck_stop = illumos_section_stop.replacen("generic_linkme", "generic_linkm2", 1);
+    let illumos_table = illumos_section.replacen("generic_linkme", "generic_linkme_tbl", 1);
+    let illumos_metadata = illumos_section.replacen("generic_linkme", "generic_linkm3", 1);
+    let illumos_metadata_start = illumos_section_start.replacen("generic_linkme", "generic_linkm3", 1);
+    let illumos_metadata_stop = illumos_section_stop.replacen("generic_linkme", "generic_linkm3", 1);
 
     let freebsd_section = linker::freebsd::section(&ident);
     let freebsd_section_start = linker::freebsd::section_start(&ident);
@@ -138,14 +214,46 @@ pub fn expand(input: TokenStream) -> TokenStream {
     let freebsd_dupcheck = freebsd_section.replacen("generic_linkme", "generic_linkm2", 1);
     let freebsd_dupcheck_start = freebsd_section_start.replacen("generic_linkme", "generic_linkm2", 1);
     let freebsd_dupcheck_stop = freebsd_section_stop.replacen("generic_linkme", "generic_linkm2", 1);
+    let freebsd_table = freebsd_section.replacen("generic_linkme", "generic_linkme_tbl", 1);
+    let freebsd_metadata = freebsd_section.replacen("generic_linkme", "generic_linkm3", 1);
+    let freebsd_metadata_start = freebsd_section_start.replacen("generic_linkme", "generic_linkm3", 1);
+    let freebsd_metadata_stop = freebsd_section_stop.replacen("generic_linkme", "generic_linkm3", 1);
+
+    // With the `link_dupcheck` feature every declaration also exports a
+    // symbol named after the slice, so that duplicates fail to link.
+    let dupcheck_symbol = if cfg!(feature = "link_dupcheck") {
+        let symbol = format!("generic_linkme_declared_{}", ident);
+        quote!(#[export_name = #symbol])
+    } else {
+        quote!()
+    };
+
+    // With the `postlink` feature every declaration reserves room for the
+    // table that `generic-linkme-postlink` fills in.
+    let (table, table_ref) = if cfg!(feature = "postlink") {
+        let table = quote! {
+            #used
+            #[cfg_attr(any(target_os = "none", target_os = "linux"), link_section = #linux_table)]
+            #[cfg_attr(target_os = "illumos", link_section = #illumos_table)]
+            #[cfg_attr(target_os = "freebsd", link_section = #freebsd_table)]
+            static TABLE: #linkme_path::__private::Table = #linkme_path::__private::Table::EMPTY;
+        };
+        (table, quote!(::core::option::Option::Some(&TABLE)))
+    } else {
+        (quote!(), quote!(::core::option::Option::None))
+    };
 
     let call_site = Span::call_site();
     let link_section_macro_str = format!("_generic_linkme_macro_{}", ident);
     let link_section_macro = Ident::new(&link_section_macro_str, call_site);
 
     quote! {
+        #object_alias
+
         #(#attrs)*
         #vis static #ident: #linkme_path::DistributedFnSlice<#ty> = {
+            #abi_check
+
             #[cfg(any(
                 target_os = "none",
                 target_os = "linux",
@@ -172,13 +280,25 @@ pub fn expand(input: TokenStream) -> TokenStream {
                 #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_name = #macho_dupcheck_start)]
                 #[cfg_attr(target_os = "illumos", link_name = #illumos_dupcheck_start)]
                 #[cfg_attr(target_os = "freebsd", link_name = #freebsd_dupcheck_start)]
-                static DUPCHECK_START: #linkme_path::__private::usize;
+                static DUPCHECK_START: #linkme_path::__private::Declaration;
 
                 #[cfg_attr(any(target_os = "none", target_os = "linux"), link_name = #linux_dupcheck_stop)]
                 #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_name = #macho_dupcheck_stop)]
                 #[cfg_attr(target_os = "illumos", link_name = #illumos_dupcheck_stop)]
                 #[cfg_attr(target_os = "freebsd", link_name = #freebsd_dupcheck_stop)]
-                static DUPCHECK_STOP: #linkme_path::__private::usize;
+                static DUPCHECK_STOP: #linkme_path::__private::Declaration;
+
+                #[cfg_attr(any(target_os = "none", target_os = "linux"), link_name = #linux_metadata_start)]
+                #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_name = #macho_metadata_start)]
+                #[cfg_attr(target_os = "illumos", link_name = #illumos_metadata_start)]
+                #[cfg_attr(target_os = "freebsd", link_name = #freebsd_metadata_start)]
+                static METADATA_START: #linkme_path::__private::Metadata;
+
+                #[cfg_attr(any(target_os = "none", target_os = "linux"), link_name = #linux_metadata_stop)]
+                #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_name = #macho_metadata_stop)]
+                #[cfg_attr(target_os = "illumos", link_name = #illumos_metadata_stop)]
+                #[cfg_attr(target_os = "freebsd", link_name = #freebsd_metadata_stop)]
+                static METADATA_STOP: #linkme_path::__private::Metadata;
             }
 
             #[cfg(target_os = "windows")]
@@ -197,6 +317,14 @@ pub fn expand(input: TokenStream) -> TokenStream {
             #[link_section = #windows_dupcheck_stop]
             static DUPCHECK_STOP: () = ();
 
+            #[cfg(target_os = "windows")]
+            #[link_section = #windows_metadata_start]
+            static METADATA_START: () = ();
+
+            #[cfg(target_os = "windows")]
+            #[link_section = #windows_metadata_stop]
+            static METADATA_STOP: () = ();
+
             #used
             #[cfg(any(target_os = "none", target_os = "linux", target_os = "illumos", target_os = "freebsd"))]
             #[cfg_attr(any(target_os = "none", target_os = "linux"), link_section = #linux_section)]
@@ -210,7 +338,23 @@ pub fn expand(input: TokenStream) -> TokenStream {
             #[cfg_attr(target_os = "windows", link_section = #windows_dupcheck)]
             #[cfg_attr(target_os = "illumos", link_section = #illumos_dupcheck)]
             #[cfg_attr(target_os = "freebsd", link_section = #freebsd_dupcheck)]
-            static DUPCHECK: #linkme_path::__private::usize = 1;
+            #dupcheck_symbol
+            static DUPCHECK: #linkme_path::__private::Declaration = #linkme_path::__private::Declaration {
+                module_path: #linkme_path::__private::module_path!(),
+                file: #linkme_path::__private::file!(),
+                line: #linkme_path::__private::line!(),
+            };
+
+            // The element metadata may be missing, so the section always
+            // exists.
+            #used
+            #[cfg(any(target_os = "none", target_os = "linux", target_os = "illumos", target_os = "freebsd"))]
+            #[cfg_attr(any(target_os = "none", target_os = "linux"), link_section = #linux_metadata)]
+            #[cfg_attr(target_os = "illumos", link_section = #illumos_metadata)]
+            #[cfg_attr(target_os = "freebsd", link_section = #freebsd_metadata)]
+            static mut METADATA_PLEASE: [#linkme_path::__private::usize; 0] = [];
+
+            #table
 
             #[cfg(not(any(
                 target_os = "none",
@@ -224,9 +368,7 @@ pub fn expand(input: TokenStream) -> TokenStream {
             )))]
             #unsupported_platform
 
-            #linkme_path::__private::assert!(
-                #linkme_path::__private::mem::size_of::<<#ty as #linkme_path::__private::Slice>::Element>() > 0,
-            );
+            #pointer_sized
 
             unsafe {
                 #linkme_path::DistributedFnSlice::private_new(
@@ -235,6 +377,10 @@ pub fn expand(input: TokenStream) -> TokenStream {
                     &LINKME_STOP,
                     &DUPCHECK_START,
                     &DUPCHECK_STOP,
+                    &METADATA_START,
+                    &METADATA_STOP,
+                    #table_ref,
+                    #object,
                 )
             }
         };
@@ -242,33 +388,13 @@ pub fn expand(input: TokenStream) -> TokenStream {
         #[doc(hidden)]
         #[macro_export]
         macro_rules! #link_section_macro {
-            (
-                #![linkme_macro = $macro:path]
-                #![linkme_sort_key = $key:tt]
-                $item:item
-            ) => {
-                $macro ! {
-                    #![linkme_linux_section = concat!(#linux_section, $key)]
-                    #![linkme_macho_section = concat!(#macho_section, $key)]
-                    #![linkme_windows_section = concat!(#windows_section, $key)]
-                    #![linkme_illumos_section = concat!(#illumos_section, $key)]
-                    #![linkme_freebsd_section = concat!(#freebsd_section, $key)]
-                    $item
-                }
-            };
-            (
-                #![linkme_linux_section = $linux_section:expr]
-                #![linkme_macho_section = $macho_section:expr]
-                #![linkme_windows_section = $windows_section:expr]
-                #![linkme_illumos_section = $illumos_section:expr]
-                #![linkme_freebsd_section = $freebsd_section:expr]
-                $item:item
-            ) => {
-                #[cfg_attr(any(target_os = "none", target_os = "linux"), link_section = $linux_section)]
-                #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_section = $macho_section)]
-                #[cfg_attr(target_os = "windows", link_section = $windows_section)]
-                #[cfg_attr(target_os = "illumos", link_section = $illumos_section)]
-                #[cfg_attr(target_os = "freebsd", link_section = $freebsd_section)]
+            (#![linkme_metadata] $item:item) => {
+                #used
+                #[cfg_attr(any(target_os = "none", target_os = "linux"), link_section = #linux_metadata)]
+                #[cfg_attr(any(target_os = "macos", target_os = "ios", target_os = "tvos"), link_section = #macho_metadata)]
+                #[cfg_attr(target_os = "windows", link_section = #windows_metadata)]
+                #[cfg_attr(target_os = "illumos", link_section = #illumos_metadata)]
+                #[cfg_attr(target_os = "freebsd", link_section = #freebsd_metadata)]
                 $item
             };
             ($item:item) => {
@@ -286,6 +412,22 @@ pub fn expand(input: TokenStream) -> TokenStream {
     }
 }
 
+// `&'static dyn Trait`, possibly with parentheses around the trait object.
+fn is_trait_object_ref(ty: &Type) -> bool {
+    fn is_trait_object(ty: &Type) -> bool {
+        match ty {
+            Type::TraitObject(_) => true,
+            Type::Paren(ty) => is_trait_object(&ty.elem),
+            Type::Group(ty) => is_trait_object(&ty.elem),
+            _ => false,
+        }
+    }
+    match ty {
+        Type::Reference(ty) => ty.mutability.is_none() && is_trait_object(&ty.elem),
+        _ => false,
+    }
+}
+
 fn populate_static_lifetimes(ty: &mut Type) {
     match ty {
         Type::Array(ty) => populate_static_lifetimes(&mut ty.elem),
@@ -0,0 +1,87 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Error, Result};
+use syn::{parse_quote, FnArg, GenericParam, Ident, ItemFn, Path, ReturnType, Type};
+
+// #[double_dispatch(TABLE)] on `fn f<A, B>(a: &A, b: &B) -> R` adds an
+// element to TABLE describing the instantiation for its two type arguments,
+// with a call that takes both values as `&dyn Any`. The function mentions the
+// element in its body, so that each of its instantiations linked into the
+// program is added to the table.
+pub fn expand(table: Path, input: ItemFn) -> TokenStream {
+    match do_expand(table, input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(table: Path, mut item: ItemFn) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let (left, right) = type_params(&item)?;
+
+    // Only `'static` types have a `TypeId`.
+    for param in item.sig.generics.type_params_mut() {
+        if param.ident == left || param.ident == right {
+            param.bounds.push(parse_quote!('static));
+        }
+    }
+
+    let ident = &item.sig.ident;
+    let element = format_ident!("{}_generic_linkme_binary", ident);
+    let mention = mention::mention(&linkme_path, quote!(#element::<#left, #right>));
+    let block = &item.block;
+    item.block = parse_quote!({
+        #mention
+        #block
+    });
+
+    let generics = &item.sig.generics;
+    let where_clause = &item.sig.generics.where_clause;
+    let output = match &item.sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice(#table)]
+        #[linkme(crate = #linkme_path)]
+        fn #element #generics () -> #linkme_path::dispatch::Binary<#output> #where_clause {
+            #linkme_path::dispatch::Binary {
+                left: #linkme_path::register_type::<#left>(),
+                right: #linkme_path::register_type::<#right>(),
+                call: |a, b| #ident::<#left, #right>(
+                    a.downcast_ref::<#left>().unwrap(),
+                    b.downcast_ref::<#right>().unwrap(),
+                ),
+            }
+        }
+    })
+}
+
+// The two type parameters, which the two arguments have to borrow in order.
+fn type_params(item: &ItemFn) -> Result<(Ident, Ident)> {
+    let sig = &item.sig;
+    let mut params = sig.generics.params.iter().filter(|param| !matches!(param, GenericParam::Lifetime(_)));
+    let (left, right) = match (params.next(), params.next(), params.next()) {
+        (Some(GenericParam::Type(left)), Some(GenericParam::Type(right)), None) => (&left.ident, &right.ident),
+        _ => {
+            let msg = "double dispatch functions take exactly two type parameters, one per argument";
+            return Err(Error::new_spanned(&sig.generics, msg));
+        }
+    };
+    let borrowed = |arg: Option<&FnArg>, param: &Ident| match arg {
+        Some(FnArg::Typed(arg)) => match &*arg.ty {
+            Type::Reference(ty) => ty.mutability.is_none() && matches!(&*ty.elem, Type::Path(path) if path.qself.is_none() && path.path.is_ident(param)),
+            _ => false,
+        },
+        _ => false,
+    };
+    let mut inputs = sig.inputs.iter();
+    if !(borrowed(inputs.next(), left) && borrowed(inputs.next(), right) && inputs.next().is_none()) {
+        let msg = format!("double dispatch functions take `&{}` and `&{}` as their arguments", left, right);
+        return Err(Error::new_spanned(&sig.inputs, msg));
+    }
+    Ok((left.clone(), right.clone()))
+}
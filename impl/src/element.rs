@@ -1,4 +1,5 @@
-use crate::attr;
+use crate::args::ElementArgs;
+use crate::{attr, mention, trampoline};
 use proc_macro2::{Span, TokenStream, TokenTree};
 use quote::{format_ident, quote, quote_spanned, ToTokens};
 use std::iter::FromIterator;
@@ -7,8 +8,10 @@ use syn::punctuated::Punctuated;
 use syn::{
     braced, parenthesized, parse_quote, Abi, Attribute, BareFnArg, BoundLifetimes, GenericParam,
     Generics, Ident, Path, ReturnType, Token, Type, TypeBareFn, Visibility, WhereClause,
-    Item, ItemFn, Lifetime,
+    FnArg, GenericArgument, Item, ItemFn, ItemImpl, ImplItem, Lifetime, Pat, PathArguments, Stmt,
+    TypePath,
 };
+use syn::spanned::Spanned;
 
 pub struct Element {
     attrs: Vec<Attribute>,
@@ -202,8 +205,12 @@ impl Parse for Element {
 }
 
 pub struct Element2 {
+    // The element as written, which is what the caller sees.
+    original: ItemFn,
+    // The element with its `impl Trait` arguments desugared, for the copies.
     item: ItemFn,
-    ty: Type,
+    sig: TypeBareFn,
+    impl_params: Vec<Ident>,
     attrs: Vec<Attribute>,
     start_span: Span,
     end_span: Span,
@@ -211,68 +218,74 @@ pub struct Element2 {
 
 impl Parse for Element2 {
     fn parse(input: ParseStream) -> Result<Self> {
-        let start_span = input.cursor().span();
-        let item: ItemFn = syn::parse2(input.cursor().token_stream())?;
-
-        let attrs = input.call(Attribute::parse_outer)?;
-        let _vis: Visibility = input.parse()?;
-        let constness: Option<Token![const]> = input.parse()?;
-        let asyncness: Option<Token![async]> = input.parse()?;
-        let unsafety: Option<Token![unsafe]> = input.parse()?;
-        let _abi: Option<Abi> = input.parse()?;
-        let fn_token: Token![fn] = input.parse().map_err(|_| {
-            Error::new_spanned(
-                item.to_token_stream(),
-                "distributed element must be a function item",
-            )
-        })?;
-        let _ident: Ident = input.parse()?;
-        let generics: Generics = input.parse()?;
-
-        let content;
-        let paren_token = parenthesized!(content in input);
-        let mut inputs = Punctuated::new();
-        while !content.is_empty() {
-            content.parse::<Option<Token![mut]>>()?;
-            let ident = if let Some(wild) = content.parse::<Option<Token![_]>>()? {
-                Ident::from(wild)
-            } else {
-                content.parse()?
-            };
-            let colon_token: Token![:] = content.parse()?;
-            let ty: Type = content.parse()?;
-            inputs.push_value(BareFnArg {
-                attrs: Vec::new(),
-                name: Some((ident, colon_token)),
-                ty,
-            });
-            if !content.is_empty() {
-                let comma: Token![,] = content.parse()?;
-                inputs.push_punct(comma);
-            }
-        }
-
-        let output: ReturnType = input.parse()?;
-        let _where_clause: Option<WhereClause> = input.parse()?;
-
-        let content;
-        braced!(content in input);
-        content.parse::<TokenStream>()?;
+        let mut item: ItemFn = syn::parse2(input.cursor().token_stream())?;
+        input.parse::<TokenStream>()?;
+        let linkme_path = attr::linkme_path(&mut item.attrs)?;
 
-        if let Some(constness) = constness {
+        if let Some(constness) = &item.sig.constness {
             return Err(Error::new_spanned(
                 constness,
                 "const fn distributed slice element is not supported",
             ));
         }
 
-        if let Some(asyncness) = asyncness {
+        if let Some(asyncness) = &item.sig.asyncness {
             return Err(Error::new_spanned(
                 asyncness,
                 "async fn distributed slice element is not supported",
             ));
         }
 
+        // `impl Trait` arguments become named type parameters, which the
+        // copies of the element can be instantiated with. The element itself
+        // keeps them, so that its callers give only its own type arguments.
+        let original = item.clone();
+        let mut impl_params = Vec::new();
+        for arg in &mut item.sig.inputs {
+            if let FnArg::Typed(arg) = arg {
+                desugar_impl_trait(&mut arg.ty, &mut impl_params);
+            }
+        }
+        if !impl_params.is_empty() {
+            let generics = &mut item.sig.generics;
+            generics.lt_token.get_or_insert_with(Default::default);
+            generics.gt_token.get_or_insert_with(Default::default);
+            for (ident, bounds) in &impl_params {
+                generics.params.push(parse_quote!(#ident: #bounds));
+            }
+        }
+        let impl_params = impl_params.into_iter().map(|(ident, _)| ident).collect();
+
+        let mut inputs = Punctuated::new();
+        for arg in item.sig.inputs.pairs() {
+            let (arg, comma) = arg.into_tuple();
+            let arg = match arg {
+                FnArg::Typed(arg) => arg,
+                FnArg::Receiver(receiver) => {
+                    return Err(Error::new_spanned(
+                        receiver,
+                        "distributed slice element cannot take `self`",
+                    ));
+                }
+            };
+            // Only plain names carry over to the signature the element is
+            // checked with; patterns are left to the element's body.
+            let name = match &*arg.pat {
+                Pat::Ident(pat) => Some(pat.ident.clone()),
+                Pat::Wild(pat) => Some(Ident::from(pat.underscore_token)),
+                _ => None,
+            };
+            inputs.push_value(BareFnArg {
+                attrs: Vec::new(),
+                name: name.map(|name| (name, arg.colon_token)),
+                ty: (*arg.ty).clone(),
+            });
+            if let Some(comma) = comma {
+                inputs.push_punct(*comma);
+            }
+        }
+
+        let generics = &item.sig.generics;
         let lifetimes = if generics.params.is_empty() {
             None
         } else {
@@ -282,25 +295,29 @@ impl Parse for Element2 {
                 lifetimes: Punctuated::new(),
                 gt_token: generics.gt_token.unwrap(),
             };
-            for param in generics.params.into_pairs() {
+            for param in generics.params.pairs() {
                 let (param, punct) = param.into_tuple();
                 if let GenericParam::Lifetime(_) = param {
-                    bound.lifetimes.push_value(param);
+                    bound.lifetimes.push_value(param.clone());
                     if let Some(punct) = punct {
-                        bound.lifetimes.push_punct(punct);
+                        bound.lifetimes.push_punct(*punct);
                     }
                 }
             }
             Some(bound)
         };
 
-        let end_span = quote!(#item)
+        // The signature, for errors about it not matching the slice.
+        let unsafety = item.sig.unsafety;
+        let fn_token = item.sig.fn_token;
+        let paren_token = item.sig.paren_token;
+        let output = item.sig.output.clone();
+        let start_span = unsafety.map_or(fn_token.span, |unsafety| unsafety.span);
+        let end_span = quote!(#output)
             .into_iter()
             .last()
             .as_ref()
             .map_or(paren_token.span.close(), TokenTree::span);
-        let mut original_attrs = attrs;
-        let linkme_path = attr::linkme_path(&mut original_attrs)?;
 
         let attrs = vec![
             parse_quote! {
@@ -311,20 +328,22 @@ impl Parse for Element2 {
             },
         ];
 
-        let ty = Type::BareFn(TypeBareFn {
+        let sig = TypeBareFn {
             lifetimes,
             unsafety,
-            abi: Some(syn::parse2(quote! {extern "sysv64"}).unwrap()),
+            abi: None,
             fn_token,
             paren_token,
             inputs,
             variadic: None,
             output,
-        });
+        };
 
         Ok(Element2 {
             attrs,
-            ty,
+            sig,
+            impl_params,
+            original,
             item,
             start_span,
             end_span,
@@ -332,7 +351,55 @@ impl Parse for Element2 {
     }
 }
 
-pub fn expand2(path: Path, pos: Option<usize>, input: Element2) -> TokenStream {
+// Replaces every `impl Trait` in an argument type with a fresh type
+// parameter, collecting the parameters and their bounds.
+fn desugar_impl_trait(ty: &mut Type, params: &mut Vec<(Ident, TokenStream)>) {
+    match ty {
+        Type::ImplTrait(impl_trait) => {
+            let ident = format_ident!("__GenericLinkmeImpl{}", params.len(), span = impl_trait.impl_token.span);
+            let bounds = &impl_trait.bounds;
+            params.push((ident.clone(), quote!(#bounds)));
+            *ty = parse_quote!(#ident);
+        }
+        Type::Array(ty) => desugar_impl_trait(&mut ty.elem, params),
+        Type::Group(ty) => desugar_impl_trait(&mut ty.elem, params),
+        Type::Paren(paren) => {
+            let is_impl_trait = matches!(*paren.elem, Type::ImplTrait(_));
+            desugar_impl_trait(&mut paren.elem, params);
+            // `&(impl A + B)` becomes `&T`.
+            if is_impl_trait {
+                *ty = (*paren.elem).clone();
+            }
+        }
+        Type::Path(ty) => {
+            if let Some(qself) = &mut ty.qself {
+                desugar_impl_trait(&mut qself.ty, params);
+            }
+            for segment in &mut ty.path.segments {
+                if let PathArguments::AngleBracketed(segment) = &mut segment.arguments {
+                    for arg in &mut segment.args {
+                        if let GenericArgument::Type(arg) = arg {
+                            desugar_impl_trait(arg, params);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Ptr(ty) => desugar_impl_trait(&mut ty.elem, params),
+        Type::Reference(ty) => desugar_impl_trait(&mut ty.elem, params),
+        Type::Slice(ty) => desugar_impl_trait(&mut ty.elem, params),
+        Type::Tuple(ty) => ty.elems.iter_mut().for_each(|ty| desugar_impl_trait(ty, params)),
+        _ => {}
+    }
+}
+
+pub fn expand2(args: ElementArgs, input: Element2) -> TokenStream {
+    if let Some(value) = &args.value {
+        return Error::new_spanned(
+            value,
+            "`value` only applies to `impl Trait for Type` elements",
+        ).to_compile_error();
+    }
     let name = input.item.sig.ident.clone();
     let type_and_const_params = input.item.sig.generics.params
         .iter().flat_map(|p| match p {
@@ -341,109 +408,347 @@ pub fn expand2(path: Path, pos: Option<usize>, input: Element2) -> TokenStream {
             _ => None
         })
         .collect::<Vec<_>>();
+    // The copies that only forward the arguments bind each of them to a
+    // plain name, leaving patterns to the copy with the element's body.
+    let mut forwarding = input.item.clone();
     let mut receiver = Vec::new();
-    let arguments = input.item.sig.inputs
-        .iter().map(|arg| {
-            match arg {
-                syn::FnArg::Receiver(r) => {
-                    receiver.push(r.self_token.clone());
-                    Ok(None)
-                },
-                syn::FnArg::Typed(pt) => {
-                    let pat = &*pt.pat;
-                    // return Some(pat.clone());
-                    match pat {
-                        syn::Pat::Ident(pi) => {
-                            if pi.by_ref.is_some() {
-                                Err(Error::new_spanned(
-                                    pi.by_ref.to_token_stream(),
-                                    "distributed_fn_slice: ref patterns in argument position are not supported",
-                                ))
-                            } else {
-                                Ok(Some(pi.ident.clone()))
-                            }
-                        },
-                        syn::Pat::Wild(_) => Err(Error::new_spanned(
-                            pat.to_token_stream(),
-                            "distributed_fn_slice: all arguments must be named",
-                        )),
-                        _ => Err(Error::new_spanned(
-                            pat.to_token_stream(),
-                            "distributed_fn_slice: destructuring arguments are not supported",
-                        )),
+    let mut arguments = Vec::new();
+    for (index, arg) in forwarding.sig.inputs.iter_mut().enumerate() {
+        match arg {
+            FnArg::Receiver(r) => receiver.push(r.self_token),
+            FnArg::Typed(pt) => {
+                let ident = match &*pt.pat {
+                    Pat::Ident(pi) if pi.by_ref.is_none() && pi.subpat.is_none() => pi.ident.clone(),
+                    _ => {
+                        let ident = format_ident!("__generic_linkme_arg{}", index);
+                        *pt.pat = parse_quote!(#ident);
+                        ident
                     }
-                },
+                };
+                arguments.push(ident);
             }
-        })
-        .collect::<Result<Vec<_>>>();
-    let arguments = match arguments {
-        Ok(args) => args.into_iter().flat_map(|a| a).collect::<Vec<_>>(),
-        Err(err) => return err.to_compile_error(),
-    };
+        }
+    }
 
-    let ty = input.ty;
+    let sig = input.sig;
     let new = quote_spanned!(input.start_span=> __new);
     let uninit = quote_spanned!(input.end_span=> #new());
-    let sort_key = pos.into_iter().map(|pos| format!("{:04}", pos));
     let linkme_path = match attr::linkme_path(&mut input.attrs.clone()) {
         Ok(path) => path,
         Err(err) => return err.to_compile_error(),
     };
 
-    let mut inner_impl = input.item.clone();
-    let inner_impl_name = format_ident!("{}_inner_impl", name);
-    inner_impl.sig.ident = inner_impl_name.clone();
-    inner_impl.vis = Visibility::Inherited;
-    let mut middle_impl = input.item.clone();
-    let middle_impl_name = format_ident!("{}_middle_impl", name);
-    middle_impl.sig.ident = middle_impl_name.clone();
-    middle_impl.vis = Visibility::Inherited;
-    middle_impl.sig.abi = Some(syn::parse2(quote! {extern "sysv64"}).unwrap());
-    middle_impl.block = Box::new(syn::parse2(quote! {{
-        fn volatile<T>(x: T) -> T { unsafe { let res = std::ptr::read_volatile(&x); std::mem::forget(x); res } }
-        volatile(
-            #inner_impl_name::<#(#type_and_const_params,)*>(
-                #(volatile(#receiver),)*#(volatile(#arguments),)*
+    // Elements with a name, position or ordering relations place a record of
+    // them in the slice's metadata section, and a function returning it in
+    // the slice's section next to their bodies, which call it first. The
+    // runtime recognizes it as the one call that stays inside the section and
+    // looks up the record of that function, without calling it.
+    let has_metadata = args.name.is_some()
+        || args.pos.is_some()
+        || !args.after.is_empty()
+        || !args.before.is_empty();
+    let metadata_name = match &args.name {
+        Some(name) => quote!(::core::option::Option::Some(#name)),
+        None => quote!(::core::option::Option::None),
+    };
+    let metadata_pos = match args.pos {
+        Some(pos) => quote!(#pos),
+        None => quote!(#linkme_path::__private::usize::MAX),
+    };
+    // Generic elements with metadata also call a function per instantiation,
+    // which gives the runtime their type arguments as a key to tell the
+    // instantiations apart by.
+    let type_params: Vec<&Ident> = input.item.sig.generics.type_params().map(|param| &param.ident).collect();
+    let has_key = has_metadata && !type_params.is_empty();
+    let key_params = input.item.sig.generics.params.iter().filter_map(|param| match param {
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+            Some(quote!(#ident: ?::core::marker::Sized))
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            let ty = &param.ty;
+            Some(quote!(const #ident: #ty))
+        }
+        GenericParam::Lifetime(_) => None,
+    });
+    let key_params = quote!(#(#key_params,)*);
+    let metadata_after = &args.after;
+    let metadata_before = &args.before;
+    // Elements that are left out keep their bodies, outside of the section.
+    let (when, unless) = match &args.when {
+        Some(when) => (quote!(#[cfg(#when)]), Some(quote!(#[cfg(not(#when))]))),
+        None => (quote!(), None),
+    };
+
+    // The copies are checked against each slice and instantiated with the
+    // element's own type parameters, while the arguments of `impl Trait` are
+    // whatever the slice makes of them.
+    let check_params = type_and_const_params.iter().map(|param| {
+        if input.impl_params.contains(param) {
+            quote!(_)
+        } else {
+            quote!(#param)
+        }
+    });
+    let check_params = quote!(#(#check_params,)*);
+    let mut pair_generics = input.item.sig.generics.clone();
+    pair_generics.params = pair_generics.params.into_iter()
+        .filter(|param| !matches!(param, GenericParam::Lifetime(_)))
+        .collect();
+    let pair_where_clause = &pair_generics.where_clause;
+    let trampoline_sig = trampoline::fn_type(&linkme_path, &sig);
+
+    let mut copies = Vec::new();
+    let mut registrations = Vec::new();
+    for (index, path) in args.slices.iter().enumerate() {
+        // One copy of the element per slice, each checked against its slice.
+        let stem = match index {
+            0 => name.clone(),
+            _ => format_ident!("{}__{}", name, index),
+        };
+
+        let mut inner_impl = input.item.clone();
+        let inner_impl_name = format_ident!("{}_inner_impl", stem);
+        inner_impl.sig.ident = inner_impl_name.clone();
+        inner_impl.vis = Visibility::Inherited;
+        inner_impl.sig.abi = None;
+        let mut middle_impl = forwarding.clone();
+        let middle_impl_name = format_ident!("{}_middle_impl", stem);
+        middle_impl.sig.ident = middle_impl_name.clone();
+        middle_impl.vis = Visibility::Inherited;
+        middle_impl.sig.abi = None;
+        middle_impl.block = Box::new(syn::parse2(quote! {{
+            fn volatile<T>(x: T) -> T { unsafe { let res = std::ptr::read_volatile(&x); std::mem::forget(x); res } }
+            volatile(
+                #inner_impl_name::<#(#type_and_const_params,)*>(
+                    #(volatile(#receiver),)*#(volatile(#arguments),)*
+                )
             )
-        )
-    }}).unwrap());
-    let mut outer_impl = input.item.clone();
-    let outer_impl_name = format_ident!("{}_generic_linkme_impl", name);
-    outer_impl.sig.ident = outer_impl_name.clone();
-    outer_impl.vis = syn::parse2(quote! {pub}).unwrap();
-    outer_impl.sig.abi = Some(syn::parse2(quote! {extern "sysv64"}).unwrap());
-    outer_impl.block = Box::new(syn::parse2(quote! {{
-        #[warn(improper_ctypes_definitions, unused_mut)] #inner_impl #[inline(never)] #middle_impl
-        unsafe fn __typecheck(_: #linkme_path::__private::Void) {
-            let #new = #linkme_path::__private::value::<#ty>;
-            #linkme_path::DistributedFnSlice::private_typecheck(&#path, #uninit)
+        }}).unwrap());
+        let key = if has_key {
+            let key_fn = format_ident!("{}_generic_linkme_key", stem);
+            let type_args_fn = format_ident!("{}_generic_linkme_type_args", stem);
+            quote! {
+                fn #type_args_fn<#key_params>() -> ::std::string::String {
+                    let type_args: &[&str] = &[#(#linkme_path::__private::type_name::<#type_params>()),*];
+                    type_args.join(", ")
+                }
+                #path ! {
+                    #[inline(never)]
+                    #[allow(improper_ctypes_definitions)]
+                    extern "C" fn #key_fn<#key_params>() -> #linkme_path::__private::Key {
+                        #linkme_path::__private::opaque_key(#type_args_fn::<#(#type_and_const_params,)*>)
+                    }
+                }
+                #linkme_path::__private::opaque_key(#key_fn::<#(#type_and_const_params,)*>());
+            }
+        } else {
+            quote!()
+        };
+        let metadata = if has_metadata {
+            let metadata_fn = format_ident!("{}_generic_linkme_meta", stem);
+            quote! {
+                #path ! {
+                    #![linkme_metadata]
+                    static METADATA: #linkme_path::__private::Metadata = #linkme_path::__private::Metadata {
+                        function: #metadata_fn,
+                        pos: #metadata_pos,
+                        name: #metadata_name,
+                        after: &[#(#metadata_after),*],
+                        before: &[#(#metadata_before),*],
+                    };
+                }
+                #path ! {
+                    #[inline(never)]
+                    #[allow(improper_ctypes_definitions)]
+                    extern "C" fn #metadata_fn() -> &'static #linkme_path::__private::Metadata {
+                        #linkme_path::__private::opaque(&METADATA)
+                    }
+                }
+                #linkme_path::__private::opaque(#metadata_fn());
+                #key
+            }
+        } else {
+            quote!()
+        };
+        let mut outer_impl = forwarding.clone();
+        let outer_impl_name = format_ident!("{}_generic_linkme_impl", stem);
+        outer_impl.sig.ident = outer_impl_name.clone();
+        outer_impl.vis = Visibility::Inherited;
+        outer_impl.sig.abi = None;
+        let middle_impl = trampoline::fn_item(&linkme_path, None, quote!(#[inline(never)]), &middle_impl);
+        let block = |metadata: &TokenStream| syn::parse2(quote! {{
+            #[warn(improper_ctypes_definitions, unused_mut)] #inner_impl #middle_impl
+            #metadata
+            #middle_impl_name::<#(#type_and_const_params,)*>(
+                #(#receiver,)*#(#arguments,)*
+            )
+        }}).unwrap();
+        let attrs = quote! {
+            #[inline(never)]
+            #[allow(improper_ctypes_definitions, unused_mut, non_snake_case)]
+        };
+        outer_impl.block = Box::new(block(&metadata));
+        copies.push(trampoline::fn_item(&linkme_path, Some(path), quote!(#when #attrs), &outer_impl));
+        if let Some(unless) = &unless {
+            outer_impl.block = Box::new(block(&quote!()));
+            copies.push(trampoline::fn_item(&linkme_path, None, quote!(#unless #attrs), &outer_impl));
         }
-        #middle_impl_name::<#(#type_and_const_params,)*>(
-            #(#receiver,)*#(#arguments,)*
-        )
-    }}).unwrap());
-    let mut rewritten_item = input.item.clone();
+
+        // The signature and the copy for the same generic arguments, which
+        // the slice checks and infers the arguments of `impl Trait` for.
+        let pair_name = format_ident!("{}_generic_linkme_pair", stem);
+        let wild = sig.inputs.iter().map(|_| quote!(_));
+        copies.push(quote! {
+            #[allow(improper_ctypes_definitions, non_snake_case)]
+            fn #pair_name #pair_generics () -> (#sig, #trampoline_sig) #pair_where_clause {
+                (
+                    |#(#wild),*| ::core::unreachable!(),
+                    #outer_impl_name::<#(#type_and_const_params,)*>,
+                )
+            }
+        });
+        // The address of the copy escapes, which instantiates it and keeps
+        // the optimizer from specializing it for the calls it sees.
+        let mention = mention::mention(&linkme_path, quote!(element));
+        registrations.push(quote! {
+            #[allow(improper_ctypes_definitions)]
+            {
+                #[allow(unused_imports)]
+                use #linkme_path::__private::{CheckElement as _, CheckSignature as _, FnSlice as _, ObjectSlice as _};
+                let (signature, element) = #pair_name::<#check_params>();
+                let #new = move || signature;
+                let typecheck = (&#path).typecheck();
+                let _ = || {
+                    let mut signature = (&typecheck).signature();
+                    signature = #uninit;
+                    signature
+                };
+                let element = (&typecheck).check(element);
+                #mention
+            }
+        });
+    }
+
+    // The copies are nested in the element, which keeps its visibility, so
+    // that they neither leak into nor clash with the enclosing module.
+    let mut rewritten_item = input.original;
+    let stmts = &rewritten_item.block.stmts;
     rewritten_item.block = Box::new(syn::parse2(quote! {{
-        #outer_impl_name::<#(#type_and_const_params,)*>(
-            #(#receiver,)*#(#arguments,)*
-        )
+        #(#copies)*
+        #(#registrations)*
+        #(#stmts)*
     }}).unwrap());
-    rewritten_item.vis = syn::parse2(quote! {pub}).unwrap();
     quote! {
-        #path ! {
-            #(
-                #![linkme_macro = #path]
-                #![linkme_sort_key = #sort_key]
-            )*
-            #[inline(never)]
-            #[allow(improper_ctypes_definitions, unused_mut)]
-            #outer_impl
-        }
-        #[allow(unused_mut)]
         #rewritten_item
     }
 }
 
+// `impl Trait for Type` in a slice of `&'static dyn Trait` adds `&value` for
+// every instantiation of the impl. It becomes a generic function element
+// returning the promoted reference, which every method of the impl mentions
+// so that using the impl for some type arguments instantiates it.
+pub fn expand_impl(mut args: ElementArgs, mut item: ItemImpl) -> TokenStream {
+    match do_expand_impl(&mut args, &mut item) {
+        Ok(element) => {
+            let element = match syn::parse2(element) {
+                Ok(element) => element,
+                Err(err) => return err.to_compile_error(),
+            };
+            let expanded = expand2(args, element);
+            quote! {
+                #item
+                #expanded
+            }
+        }
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand_impl(args: &mut ElementArgs, item: &mut ItemImpl) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let trait_path = match &item.trait_ {
+        Some((None, path, _)) => path,
+        Some((Some(bang), _, _)) => {
+            return Err(Error::new_spanned(bang, "negative impls cannot be distributed elements"));
+        }
+        None => {
+            return Err(Error::new_spanned(
+                &item.self_ty,
+                "distributed element impl must implement the trait of the slice",
+            ));
+        }
+    };
+    let self_path = match &*item.self_ty {
+        Type::Path(TypePath { qself: None, path }) => Some(path),
+        _ => None,
+    };
+    let value = match (args.value.take(), self_path) {
+        (Some(value), _) => value.into_token_stream(),
+        // `Type` for a unit struct, `Type::<T>(PhantomData)` for the usual
+        // marker of a type argument.
+        (None, Some(path)) => {
+            let mut path = path.clone();
+            let last = path.segments.last_mut().unwrap();
+            match &mut last.arguments {
+                PathArguments::AngleBracketed(arguments) => {
+                    arguments.colon2_token = Some(Token![::](arguments.lt_token.span));
+                    quote!(#path(#linkme_path::__private::PhantomData))
+                }
+                _ => path.into_token_stream(),
+            }
+        }
+        (None, None) => {
+            return Err(Error::new_spanned(
+                &item.self_ty,
+                "give the object to add with `value = <expr>`",
+            ));
+        }
+    };
+
+    let self_ident = self_path
+        .and_then(|path| path.segments.last())
+        .map_or_else(|| format_ident!("Type"), |segment| segment.ident.clone());
+    let trait_ident = &trait_path.segments.last().unwrap().ident;
+    let name = format_ident!("{}_as_{}", self_ident, trait_ident);
+    // The declaration names the type of its objects after the slice.
+    let object = &args.slices[0];
+
+    let type_and_const_params = item.generics.params.iter().flat_map(|param| match param {
+        GenericParam::Type(param) => Some(&param.ident),
+        GenericParam::Const(param) => Some(&param.ident),
+        GenericParam::Lifetime(_) => None,
+    });
+    // The methods register the element when the impl is instantiated, so an
+    // impl without any would never be added to the slice.
+    if !item.items.iter().any(|impl_item| matches!(impl_item, ImplItem::Fn(_))) {
+        return Err(Error::new_spanned(
+            &item.self_ty,
+            "distributed element impl must define at least one method, which adds it to the slice",
+        ));
+    }
+    let mention: Stmt = syn::parse2(mention::mention(
+        &linkme_path,
+        quote!(#name::<#(#type_and_const_params,)*>),
+    ))?;
+    for impl_item in &mut item.items {
+        if let ImplItem::Fn(method) = impl_item {
+            method.block.stmts.insert(0, mention.clone());
+        }
+    }
+
+    let params = &item.generics.params;
+    let where_clause = &item.generics.where_clause;
+    Ok(quote_spanned! {item.self_ty.span()=>
+        #[allow(non_snake_case)]
+        #[linkme(crate = #linkme_path)]
+        fn #name<#params>() -> #object #where_clause {
+            &#value
+        }
+    })
+}
+
 pub fn expand(path: Path, pos: impl Into<Option<usize>>, input: Element) -> TokenStream {
     let pos = pos.into();
     do_expand(path, pos, input)
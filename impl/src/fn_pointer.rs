@@ -0,0 +1,91 @@
+use crate::{attr, trampoline};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Result, Type};
+
+// #[derive(FnPointer)] on a `#[repr(transparent)]` struct with a single bare
+// function pointer field. The trampoline type is derived from the field, and
+// elements are checked against the field type.
+pub fn expand(input: DeriveInput) -> TokenStream {
+    match do_expand(input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(mut input: DeriveInput) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut input.attrs)?;
+    let ident = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "FnPointer cannot be derived for generic types",
+        ));
+    }
+
+    let transparent = input.attrs.iter().any(|attr| {
+        let mut transparent = false;
+        if attr.path().is_ident("repr") {
+            let _ = attr.parse_nested_meta(|meta| {
+                transparent |= meta.path.is_ident("transparent");
+                Ok(())
+            });
+        }
+        transparent
+    });
+    if !transparent {
+        return Err(Error::new_spanned(
+            ident,
+            "FnPointer can only be derived for #[repr(transparent)] structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "FnPointer can only be derived for structs",
+            ))
+        }
+    };
+    let field = match fields {
+        Fields::Named(fields) if fields.named.len() == 1 => &fields.named[0],
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0],
+        _ => {
+            return Err(Error::new_spanned(
+                fields,
+                "FnPointer requires exactly one field, the function pointer",
+            ))
+        }
+    };
+    let fn_ty = match &field.ty {
+        Type::BareFn(fn_ty) => fn_ty,
+        ty => {
+            return Err(Error::new_spanned(
+                ty,
+                "FnPointer requires the field to be a bare function pointer",
+            ))
+        }
+    };
+    if let Some(abi) = &fn_ty.abi {
+        return Err(Error::new_spanned(
+            abi,
+            "FnPointer requires a function pointer without an explicit ABI",
+        ));
+    }
+
+    let trampoline_ty = trampoline::fn_type(&linkme_path, fn_ty);
+    Ok(quote! {
+        unsafe impl #linkme_path::FnPointer for #ident {
+            type Trampoline = #trampoline_ty;
+            type Signature = #fn_ty;
+        }
+
+        const _: () = #linkme_path::__private::assert!(
+            #linkme_path::__private::mem::size_of::<#ident>()
+                == #linkme_path::__private::mem::size_of::<#linkme_path::__private::usize>(),
+        );
+    })
+}
@@ -0,0 +1,92 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, GenericParam, ItemFn, ReturnType, Token, Type};
+
+mod kw {
+    syn::custom_keyword!(types);
+}
+
+// types = [u8, String, Vec<u32>]
+pub struct TypesArgs {
+    types: Punctuated<Type, Token![,]>,
+}
+
+impl Parse for TypesArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<kw::types>()?;
+        input.parse::<Token![=]>()?;
+        let content;
+        bracketed!(content in input);
+        let types = content.parse_terminated(Type::parse, Token![,])?;
+        Ok(TypesArgs { types })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Kind {
+    Test,
+    Bench,
+}
+
+// #[generic_test(types = [...])] and #[generic_bench(types = [...])] add a
+// generic element to the harness's slice of tests or benchmarks, describing
+// the function for its type argument, and link it for every type in the list.
+pub fn expand(kind: Kind, args: TypesArgs, input: ItemFn) -> TokenStream {
+    match do_expand(kind, args, input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(kind: Kind, args: TypesArgs, mut item: ItemFn) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let (what, slice, suffix) = match kind {
+        Kind::Test => ("tests", quote!(GENERIC_LINKME_TESTS), "test"),
+        Kind::Bench => ("benchmarks", quote!(GENERIC_LINKME_BENCHES), "bench"),
+    };
+    let sig = &item.sig;
+    if !sig.inputs.is_empty() {
+        let msg = format!("generic {} cannot take arguments", what);
+        return Err(Error::new_spanned(&sig.inputs, msg));
+    }
+    // Benchmarks may return what they computed, which is kept from the
+    // optimizer.
+    if let (Kind::Test, ReturnType::Type(_, ty)) = (kind, &sig.output) {
+        return Err(Error::new_spanned(ty, "generic tests cannot return a value"));
+    }
+    let mut params = sig.generics.params.iter();
+    let param = match (params.next(), params.next()) {
+        (Some(GenericParam::Type(param)), None) => &param.ident,
+        _ => {
+            let msg = format!("generic {} take exactly one type parameter, given by `types`", what);
+            return Err(Error::new_spanned(&sig.generics, msg));
+        }
+    };
+
+    let ident = &sig.ident;
+    let element = format_ident!("{}_generic_linkme_{}", ident, suffix);
+    let generics = &sig.generics;
+    let where_clause = &sig.generics.where_clause;
+    let link = mention::link_once(&linkme_path, args.types.iter().map(|ty| quote!(#element::<#ty>)));
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice(#linkme_path::__private::#slice)]
+        #[linkme(crate = #linkme_path)]
+        fn #element #generics () -> #linkme_path::__private::Case #where_clause {
+            #linkme_path::__private::Case {
+                module_path: #linkme_path::__private::module_path!(),
+                name: ::core::stringify!(#ident),
+                type_name: #linkme_path::__private::type_name::<#param>(),
+                run: || {
+                    #linkme_path::__private::black_box(#ident::<#param>());
+                },
+            }
+        }
+
+        #link
+    })
+}
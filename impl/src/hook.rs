@@ -0,0 +1,72 @@
+use crate::attr;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::{parse_quote, Ident, ItemFn, LitInt, LitStr, Path, Token};
+
+// `SLICE`, followed by `priority = 10` and `name = "flush"` in any order.
+pub struct HookArgs {
+    slice: Path,
+    priority: Option<LitInt>,
+    name: Option<LitStr>,
+}
+
+impl Parse for HookArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let slice = input.parse()?;
+        let mut priority = None;
+        let mut name = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let duplicate = match key.to_string().as_str() {
+                "priority" => priority.replace(input.parse()?).is_some(),
+                "name" => name.replace(input.parse()?).is_some(),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        format!("unknown hook argument `{}`, expected `priority` or `name`", key),
+                    ));
+                }
+            };
+            if duplicate {
+                return Err(Error::new(key.span(), format!("duplicate `{}` argument", key)));
+            }
+        }
+        Ok(HookArgs {
+            slice,
+            priority,
+            name,
+        })
+    }
+}
+
+// #[hook(SLICE, priority = N)] is an element of SLICE with the priority as
+// its `pos` and its name, by default the function's, as its `name`, which
+// `hooks::run` orders the handlers by. The body runs in a closure that
+// catches its panics.
+pub fn expand(args: HookArgs, mut item: ItemFn) -> TokenStream {
+    let linkme_path = match attr::linkme_path(&mut item.attrs) {
+        Ok(path) => path,
+        Err(err) => return err.to_compile_error(),
+    };
+    let slice = &args.slice;
+    let pos = args.priority.iter();
+    let name = match args.name {
+        Some(name) => name,
+        None => LitStr::new(&item.sig.ident.to_string(), item.sig.ident.span()),
+    };
+    let block = &item.block;
+    item.block = parse_quote!({
+        #linkme_path::hooks::private_catch(move || #block)
+    });
+    quote! {
+        #[#linkme_path::distributed_fn_slice(#slice, #(pos = #pos,)* name = #name)]
+        #[linkme(crate = #linkme_path)]
+        #item
+    }
+}
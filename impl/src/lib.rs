@@ -8,15 +8,27 @@
 
 mod args;
 mod attr;
+#[cfg(feature = "c_api")]
+mod c_registry;
+mod command;
 mod declaration;
+mod double_dispatch;
 mod element;
+mod fn_pointer;
+mod harness;
 mod hash;
+mod hook;
 mod linker;
+mod mention;
+mod provider;
+#[cfg(feature = "serde")]
+mod serde_registry;
+mod trampoline;
 
 use crate::args::Args;
 use crate::hash::hash;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{parse_macro_input, DeriveInput, ItemImpl};
 
 #[proc_macro_attribute]
 pub fn distributed_fn_slice(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -24,9 +36,67 @@ pub fn distributed_fn_slice(args: TokenStream, input: TokenStream) -> TokenStrea
 
     let expanded = match args {
         Args::None => declaration::expand(parse_macro_input!(input)),
-        Args::Path(path) => element::expand2(path, None, parse_macro_input!(input)),
-        Args::PathPos(path, pos) => element::expand2(path, Some(pos), parse_macro_input!(input)),
+        Args::Element(args) => match syn::parse::<ItemImpl>(input.clone()) {
+            Ok(item) => element::expand_impl(*args, item),
+            Err(_) => element::expand2(*args, parse_macro_input!(input)),
+        },
     };
 
     TokenStream::from(expanded)
 }
+
+#[proc_macro_derive(FnPointer, attributes(linkme))]
+pub fn derive_fn_pointer(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(fn_pointer::expand(input))
+}
+
+#[proc_macro_attribute]
+pub fn generic_test(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as harness::TypesArgs);
+    TokenStream::from(harness::expand(harness::Kind::Test, args, parse_macro_input!(input)))
+}
+
+#[proc_macro_attribute]
+pub fn generic_bench(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as harness::TypesArgs);
+    TokenStream::from(harness::expand(harness::Kind::Bench, args, parse_macro_input!(input)))
+}
+
+#[proc_macro_attribute]
+pub fn double_dispatch(args: TokenStream, input: TokenStream) -> TokenStream {
+    let table = parse_macro_input!(args as syn::Path);
+    TokenStream::from(double_dispatch::expand(table, parse_macro_input!(input)))
+}
+
+#[proc_macro_attribute]
+pub fn hook(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as hook::HookArgs);
+    TokenStream::from(hook::expand(args, parse_macro_input!(input)))
+}
+
+#[cfg(feature = "c_api")]
+#[proc_macro_attribute]
+pub fn c_registry(args: TokenStream, input: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(args as syn::LitStr);
+    TokenStream::from(c_registry::expand(name, parse_macro_input!(input)))
+}
+
+#[proc_macro_attribute]
+pub fn command(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as command::CommandArgs);
+    TokenStream::from(command::expand(args, parse_macro_input!(input)))
+}
+
+#[proc_macro_attribute]
+pub fn provider(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as provider::ProviderArgs);
+    TokenStream::from(provider::expand(args, parse_macro_input!(input)))
+}
+
+#[cfg(feature = "serde")]
+#[proc_macro_attribute]
+pub fn serde_registry(args: TokenStream, input: TokenStream) -> TokenStream {
+    parse_macro_input!(args as syn::parse::Nothing);
+    TokenStream::from(serde_registry::expand(parse_macro_input!(input)))
+}
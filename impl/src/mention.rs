@@ -0,0 +1,33 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Path;
+
+// Elements are only linked once they are mentioned. The attributes that add
+// elements on behalf of an item mention them in one of two ways.
+
+// From a static that is kept but never read, which links the given elements
+// once, for items that are not generic or whose instantiations are listed.
+pub(crate) fn link_once<I>(linkme_path: &Path, elements: I) -> TokenStream
+where
+    I: IntoIterator,
+    I::Item: quote::ToTokens,
+{
+    let elements = elements.into_iter();
+    quote! {
+        const _: () = {
+            #[used]
+            static LINK: fn() = || {
+                #(#linkme_path::link(#elements);)*
+            };
+        };
+    }
+}
+
+// As a statement taking the address of the element, which inserted into a
+// generic function links the element for every instantiation of it. The
+// address escapes, which keeps the optimizer from removing the statement.
+pub(crate) fn mention(linkme_path: &Path, element: TokenStream) -> TokenStream {
+    quote! {
+        #linkme_path::__private::black_box(#element as #linkme_path::__private::usize);
+    }
+}
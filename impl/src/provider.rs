@@ -0,0 +1,164 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::{parse_quote, GenericArgument, GenericParam, Ident, ItemFn, Path, PathArguments, ReturnType, Token, Type, TypeParamBound};
+
+// `SLICE`, optionally followed by `transient`.
+pub struct ProviderArgs {
+    slice: Path,
+    transient: bool,
+}
+
+impl Parse for ProviderArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let slice = input.parse()?;
+        let mut transient = false;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let flag: Ident = input.parse()?;
+            if flag != "transient" {
+                return Err(Error::new(
+                    flag.span(),
+                    format!("unknown provider argument `{}`, expected `transient`", flag),
+                ));
+            }
+            if transient {
+                return Err(Error::new(flag.span(), "duplicate `transient` argument"));
+            }
+            transient = true;
+        }
+        Ok(ProviderArgs { slice, transient })
+    }
+}
+
+// #[provider(SLICE)] on a function returning a value, optionally taking the
+// container to resolve its dependencies from, adds an element to SLICE that
+// provides its return type. A generic provider mentions the element for its
+// type arguments in its body, so that every instantiation linked into the
+// program provides the type it returns.
+pub fn expand(args: ProviderArgs, input: ItemFn) -> TokenStream {
+    match do_expand(args, input) {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn do_expand(args: ProviderArgs, mut item: ItemFn) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let output = match &item.sig.output {
+        ReturnType::Type(_, ty) => ty.clone(),
+        ReturnType::Default => return Err(Error::new_spanned(&item.sig, "providers return the value they provide")),
+    };
+    // A factory returning `Box<dyn Any>` provides its one type parameter,
+    // which the box has to hold. The container hands values out from other
+    // threads too, so the parameter is bounded by `Send + Sync`.
+    let boxed_any = is_boxed_any(&output);
+    let provided = if boxed_any {
+        let mut type_params = item.sig.generics.type_params_mut();
+        let provided = match (type_params.next(), type_params.next()) {
+            (Some(param), None) => param,
+            _ => {
+                let msg = "a provider returning `Box<dyn Any>` provides its type parameter, and has to have exactly one";
+                return Err(Error::new_spanned(&output, msg));
+            }
+        };
+        provided.bounds.push(parse_quote!(::core::marker::Send));
+        provided.bounds.push(parse_quote!(::core::marker::Sync));
+        provided.bounds.push(parse_quote!('static));
+        let provided = &provided.ident;
+        quote!(#provided)
+    } else {
+        quote!(#output)
+    };
+    let sig = &item.sig;
+    let container = match sig.inputs.len() {
+        0 => None,
+        1 => Some(quote!(container)),
+        _ => {
+            let msg = "providers take no arguments, or the `&Container` to resolve their dependencies from";
+            return Err(Error::new_spanned(&sig.inputs, msg));
+        }
+    };
+    let mut params = Vec::new();
+    for param in &sig.generics.params {
+        match param {
+            GenericParam::Type(param) => params.push(&param.ident),
+            GenericParam::Const(param) => params.push(&param.ident),
+            GenericParam::Lifetime(param) => {
+                return Err(Error::new_spanned(param, "providers cannot have lifetime parameters"));
+            }
+        }
+    }
+
+    let ident = &sig.ident;
+    let element = format_ident!("{}_generic_linkme_provider", ident);
+    let generics = sig.generics.clone();
+    let where_clause = &generics.where_clause;
+    let create = if boxed_any {
+        quote! {
+            let value = #ident::<#(#params),*>(#container);
+            match value.downcast::<#provided>() {
+                ::core::result::Result::Ok(value) => #linkme_path::__private::Arc::new(*value),
+                ::core::result::Result::Err(_) => ::core::panic!(
+                    "provider `{}` returned a `Box<dyn Any>` that does not hold a `{}`",
+                    ::core::stringify!(#ident),
+                    #linkme_path::__private::type_name::<#provided>(),
+                ),
+            }
+        }
+    } else {
+        quote!(#linkme_path::__private::Arc::new(#ident::<#(#params),*>(#container)))
+    };
+    let singleton = !args.transient;
+    let slice = &args.slice;
+    // Elements are only linked once they are mentioned, a generic one for
+    // each instantiation of the provider and otherwise once.
+    let link = if params.is_empty() {
+        mention::link_once(&linkme_path, [&element])
+    } else {
+        let mention = mention::mention(&linkme_path, quote!(#element::<#(#params),*>));
+        let block = &item.block;
+        item.block = parse_quote!({
+            #mention
+            #block
+        });
+        quote!()
+    };
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice(#slice)]
+        #[linkme(crate = #linkme_path)]
+        fn #element #generics () -> #linkme_path::di::Provider #where_clause {
+            #linkme_path::di::Provider {
+                type_info: #linkme_path::register_type::<#provided>(),
+                singleton: #singleton,
+                create: |container| {
+                    let _ = container;
+                    #create
+                },
+            }
+        }
+
+        #link
+    })
+}
+
+// `Box<dyn Any>`, with any auto traits, by the last segments of the paths.
+fn is_boxed_any(ty: &Type) -> bool {
+    let Type::Path(ty) = ty else { return false };
+    let Some(segment) = ty.path.segments.last() else { return false };
+    let PathArguments::AngleBracketed(args) = &segment.arguments else { return false };
+    if segment.ident != "Box" || args.args.len() != 1 {
+        return false;
+    }
+    let Some(GenericArgument::Type(Type::TraitObject(object))) = args.args.first() else { return false };
+    object.bounds.iter().any(|bound| match bound {
+        TypeParamBound::Trait(bound) => matches!(bound.path.segments.last(), Some(segment) if segment.ident == "Any"),
+        _ => false,
+    })
+}
@@ -0,0 +1,141 @@
+use crate::{attr, mention};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Error, Item, ItemImpl, ItemTrait, Result};
+
+// #[serde_registry] on a trait declares a distributed slice of registrations,
+// one per type tag, with a generic element that registers a type given as its
+// type argument. The trait gets hidden methods returning the tag and the
+// implementor as an erased value, which #[serde_registry] on an impl
+// provides. Both mention the element, so that building the vtable of an
+// instantiation of the impl also registers it.
+pub fn expand(input: Item) -> TokenStream {
+    let expanded = match input {
+        Item::Trait(item) => expand_trait(item),
+        Item::Impl(item) => expand_impl(item),
+        item => Err(Error::new_spanned(
+            item,
+            "#[serde_registry] can only be used on a trait or an impl of it",
+        )),
+    };
+    match expanded {
+        Ok(expanded) => expanded,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn expand_trait(mut item: ItemTrait) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &item.generics,
+            "serde registry traits cannot be generic",
+        ));
+    }
+
+    let ident = &item.ident;
+    let slice = format_ident!("_GENERIC_LINKME_SERDE_{}", ident.to_string().to_uppercase());
+    let register = format_ident!("{}_generic_linkme_register", ident);
+    item.items.push(parse_quote! {
+        #[doc(hidden)]
+        fn __generic_linkme_tag(&self) -> &'static str;
+    });
+    item.items.push(parse_quote! {
+        #[doc(hidden)]
+        fn __generic_linkme_serialize(&self) -> &dyn #linkme_path::__private::erased_serde::Serialize;
+    });
+
+    let serde = quote!(#linkme_path::__private::serde);
+    let registration = quote!(#linkme_path::serde_registry::Registration<dyn #ident>);
+    let mention = mention::mention(&linkme_path, quote!(#register::<T>));
+    Ok(quote! {
+        #item
+
+        #[#linkme_path::distributed_fn_slice]
+        #[linkme(crate = #linkme_path)]
+        static #slice: [fn() -> #registration] = [..];
+
+        #[#linkme_path::distributed_fn_slice(#slice)]
+        #[linkme(crate = #linkme_path)]
+        #[allow(non_snake_case)]
+        fn #register<T: #ident + #serde::de::DeserializeOwned + 'static>() -> #registration {
+            #linkme_path::serde_registry::Registration::private_new(
+                #linkme_path::__private::type_name::<T>(),
+                |deserializer| {
+                    let value: ::std::boxed::Box<dyn #ident> = ::std::boxed::Box::new(
+                        #linkme_path::__private::erased_serde::deserialize::<T>(deserializer)?,
+                    );
+                    ::core::result::Result::Ok(value)
+                },
+            )
+        }
+
+        impl #linkme_path::serde_registry::Registry for dyn #ident {
+            const NAME: &'static str = ::core::stringify!(#ident);
+
+            fn registrations() -> ::std::vec::Vec<#registration> {
+                #slice.iter().map(|register| register()).collect()
+            }
+        }
+
+        impl<T: #ident + #serde::de::DeserializeOwned + 'static> #linkme_path::__private::Register<T> for dyn #ident {
+            fn register() -> &'static str {
+                #mention
+                #linkme_path::__private::type_name::<T>()
+            }
+        }
+
+        impl #serde::Serialize for dyn #ident {
+            fn serialize<S: #serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> ::core::result::Result<S::Ok, S::Error> {
+                #linkme_path::serde_registry::serialize(
+                    self.__generic_linkme_tag(),
+                    self.__generic_linkme_serialize(),
+                    serializer,
+                )
+            }
+        }
+
+        impl<'de> #serde::Deserialize<'de> for ::std::boxed::Box<dyn #ident> {
+            fn deserialize<D: #serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::core::result::Result<Self, D::Error> {
+                #linkme_path::serde_registry::deserialize::<dyn #ident, D>(deserializer)
+            }
+        }
+    })
+}
+
+fn expand_impl(mut item: ItemImpl) -> Result<TokenStream> {
+    let linkme_path = attr::linkme_path(&mut item.attrs)?;
+    let trait_path = match &item.trait_ {
+        Some((None, path, _)) => path,
+        Some((Some(bang), _, _)) => {
+            return Err(Error::new_spanned(bang, "negative impls cannot be registered"));
+        }
+        None => {
+            return Err(Error::new_spanned(
+                &item.self_ty,
+                "#[serde_registry] impl must implement the trait of the registry",
+            ));
+        }
+    };
+
+    let register = quote! {
+        <dyn #trait_path as #linkme_path::__private::Register<Self>>::register()
+    };
+    item.items.push(parse_quote! {
+        fn __generic_linkme_tag(&self) -> &'static str {
+            #register
+        }
+    });
+    item.items.push(parse_quote! {
+        fn __generic_linkme_serialize(&self) -> &dyn #linkme_path::__private::erased_serde::Serialize {
+            #register;
+            self
+        }
+    });
+    Ok(quote!(#item))
+}
@@ -0,0 +1,54 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ItemFn, Path, Type, TypeBareFn};
+
+// The calling convention of the trampolines depends on the target
+// architecture, which is not known to the macro, so the runtime crate's
+// `trampoline!` macro inserts it after target cfgs have been resolved.
+
+pub(crate) fn fn_type(linkme_path: &Path, ty: &TypeBareFn) -> Type {
+    let TypeBareFn {
+        lifetimes,
+        unsafety,
+        fn_token,
+        inputs,
+        output,
+        ..
+    } = ty;
+    Type::Verbatim(quote! {
+        #linkme_path::__private::trampoline! {
+            { #lifetimes #unsafety }
+            { #fn_token (#inputs) #output }
+        }
+    })
+}
+
+pub(crate) fn fn_item(
+    linkme_path: &Path,
+    callback: Option<&Path>,
+    prefix: TokenStream,
+    item: &ItemFn,
+) -> TokenStream {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    let constness = &sig.constness;
+    let asyncness = &sig.asyncness;
+    let unsafety = &sig.unsafety;
+    let fn_token = &sig.fn_token;
+    let ident = &sig.ident;
+    let generics = &sig.generics;
+    let where_clause = &sig.generics.where_clause;
+    let inputs = &sig.inputs;
+    let output = &sig.output;
+    quote! {
+        #linkme_path::__private::trampoline! {
+            #callback
+            { #prefix #(#attrs)* #vis #constness #asyncness #unsafety }
+            { #fn_token #ident #generics (#inputs) #output #where_clause #block }
+        }
+    }
+}
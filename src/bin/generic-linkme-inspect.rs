@@ -0,0 +1,34 @@
+//! Usage: `generic-linkme-inspect <file>...`
+//!
+//! Describes the distributed slices of each ELF `file`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+fn main() {
+    let files: Vec<PathBuf> = env::args_os().skip(1).map(PathBuf::from).collect();
+    if files.is_empty() {
+        eprintln!("usage: generic-linkme-inspect <file>...");
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for file in &files {
+        match generic_linkme::inspect::inspect(file) {
+            Ok(report) => {
+                if files.len() > 1 {
+                    println!("{}:", file.display());
+                }
+                print!("{}", report);
+            }
+            Err(err) => {
+                eprintln!("generic-linkme-inspect: {}: {}", file.display(), err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
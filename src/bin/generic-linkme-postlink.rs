@@ -0,0 +1,30 @@
+//! Usage: `generic-linkme-postlink [--run] <file> [args...]`
+//!
+//! Fills in the distributed slice tables of `file`. With `--run`, then
+//! executes it with `args`, which makes it usable as a cargo runner.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+fn main() {
+    let mut args = env::args_os().skip(1).peekable();
+    let run = args.next_if(|arg| arg == "--run").is_some();
+    let Some(file) = args.next().map(PathBuf::from) else {
+        eprintln!("usage: generic-linkme-postlink [--run] <file> [args...]");
+        process::exit(2);
+    };
+
+    if let Err(err) = generic_linkme::postlink::patch(&file) {
+        eprintln!("generic-linkme-postlink: {}: {}", file.display(), err);
+        process::exit(1);
+    }
+
+    if run {
+        let status = Command::new(&file).args(args).status().unwrap_or_else(|err| {
+            eprintln!("generic-linkme-postlink: {}: {}", file.display(), err);
+            process::exit(1);
+        });
+        process::exit(status.code().unwrap_or(1));
+    }
+}
@@ -0,0 +1,207 @@
+//! A stable C API over the slices that `#[c_registry]` exports, for
+//! enumerating their elements from non-Rust code, and the header declaring
+//! it.
+//!
+//! The elements are the trampolines of the slice, whose calling convention
+//! is `sysv64` on x86-64, `fastcall` on x86 and C elsewhere, which the
+//! header spells out for every registry.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt::{self, Write};
+use std::ptr;
+
+use once_cell::sync::Lazy;
+
+// Only this crate sees the trampoline ABI of the elements.
+#[doc(hidden)]
+#[crate::distributed_fn_slice]
+#[linkme(crate = crate)]
+#[allow(improper_ctypes_definitions)]
+pub static GENERIC_LINKME_REGISTRIES: [fn() -> Export] = [..];
+
+// Returned by the element that `#[c_registry]` adds for a slice.
+#[doc(hidden)]
+pub struct Export {
+    pub name: &'static str,
+    // C types of the element signature.
+    pub ret: &'static str,
+    pub args: &'static [&'static str],
+    pub len: fn() -> usize,
+    pub address: fn(usize) -> usize,
+    pub element_name: fn(usize) -> Option<&'static str>,
+}
+
+/// An exported slice, to C an opaque `struct generic_linkme_registry`.
+pub struct Registry {
+    name: &'static str,
+    ret: &'static str,
+    args: &'static [&'static str],
+    addresses: Vec<usize>,
+    names: Vec<Option<CString>>,
+}
+
+impl Registry {
+    /// The name of the slice.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The number of elements of the slice.
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Whether the slice has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// The C declaration of a pointer to an element, `declarator` being its
+    /// name: `int32_t (*handler)(int32_t)` with the calling convention.
+    pub fn declaration(&self, declarator: &str) -> String {
+        let args = match self.args {
+            [] => "void".to_owned(),
+            args => args.join(", "),
+        };
+        format!("{} ({}*{})({})", self.ret, CALLING_CONVENTION, declarator, args)
+    }
+}
+
+/// Two slices exported under the same name, which leaves the name without a
+/// registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The name both slices are exported under.
+    pub name: &'static str,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "more than one slice is exported as `{}`", self.name)
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+#[cfg(target_arch = "x86_64")]
+const CALLING_CONVENTION: &str = "__attribute__((sysv_abi)) ";
+#[cfg(target_arch = "x86")]
+const CALLING_CONVENTION: &str = "__attribute__((fastcall)) ";
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+const CALLING_CONVENTION: &str = "";
+
+// The elements are read once, so that the pointers handed out stay valid.
+static REGISTRIES: Lazy<Result<Vec<Registry>, Conflict>> = Lazy::new(|| {
+    let mut registries: Vec<Registry> = GENERIC_LINKME_REGISTRIES
+        .iter()
+        .map(|export| {
+            let export = export();
+            Registry {
+                name: export.name,
+                ret: export.ret,
+                args: export.args,
+                addresses: (0..(export.len)()).map(export.address).collect(),
+                names: (0..(export.len)())
+                    .map(|index| (export.element_name)(index).and_then(|name| CString::new(name).ok()))
+                    .collect(),
+            }
+        })
+        .collect();
+    registries.sort_by_key(|registry| registry.name);
+    match registries.windows(2).find(|pair| pair[0].name == pair[1].name) {
+        Some(pair) => Err(Conflict { name: pair[0].name }),
+        None => Ok(registries),
+    }
+});
+
+/// The exported slices, ordered by name, or the first name that more than one
+/// slice is exported under.
+pub fn registries() -> Result<&'static [Registry], Conflict> {
+    match &*REGISTRIES {
+        Ok(registries) => Ok(registries),
+        Err(conflict) => Err(conflict.clone()),
+    }
+}
+
+/// The registry exported under `name`, or NULL. Every name is NULL while two
+/// slices are exported under the same one, as [`registries`] reports.
+///
+/// # Safety
+///
+/// `name` is a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn generic_linkme_registry(name: *const c_char) -> Option<&'static Registry> {
+    if name.is_null() {
+        return None;
+    }
+    let name = CStr::from_ptr(name).to_str().ok()?;
+    registries().ok()?.iter().find(|registry| registry.name == name)
+}
+
+/// The number of elements in `registry`, 0 for NULL.
+#[no_mangle]
+pub extern "C" fn generic_linkme_registry_len(registry: Option<&Registry>) -> usize {
+    registry.map_or(0, Registry::len)
+}
+
+/// The element at `index` in `registry`, to be cast to its function pointer
+/// type from the header. NULL if `index` is out of bounds.
+#[no_mangle]
+pub extern "C" fn generic_linkme_registry_get(registry: Option<&Registry>, index: usize) -> *const c_void {
+    match registry.and_then(|registry| registry.addresses.get(index)) {
+        Some(&address) => address as *const c_void,
+        None => ptr::null(),
+    }
+}
+
+/// The `name` of the element at `index` in `registry`. NULL if it has none
+/// or `index` is out of bounds.
+#[no_mangle]
+pub extern "C" fn generic_linkme_registry_name(registry: Option<&Registry>, index: usize) -> *const c_char {
+    match registry.and_then(|registry| registry.names.get(index)) {
+        Some(Some(name)) => name.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+/// A C header declaring the functions above and, for every registry, the
+/// function pointer type `generic_linkme_<name>_fn` of its elements.
+pub fn header() -> Result<String, Conflict> {
+    let mut header = String::from(
+        "/* Generated by generic_linkme. */\n\
+         #ifndef GENERIC_LINKME_REGISTRY_H\n\
+         #define GENERIC_LINKME_REGISTRY_H\n\
+         \n\
+         #include <stdbool.h>\n\
+         #include <stddef.h>\n\
+         #include <stdint.h>\n\
+         \n\
+         #ifdef __cplusplus\n\
+         extern \"C\" {\n\
+         #endif\n\
+         \n\
+         struct generic_linkme_registry;\n\
+         \n\
+         const struct generic_linkme_registry *generic_linkme_registry(const char *name);\n\
+         size_t generic_linkme_registry_len(const struct generic_linkme_registry *registry);\n\
+         const void *generic_linkme_registry_get(const struct generic_linkme_registry *registry, size_t index);\n\
+         const char *generic_linkme_registry_name(const struct generic_linkme_registry *registry, size_t index);\n",
+    );
+    for registry in registries()? {
+        let _ = write!(
+            header,
+            "\n/* Elements of the registry \"{}\". */\ntypedef {};\n",
+            registry.name,
+            registry.declaration(&format!("generic_linkme_{}_fn", registry.name)),
+        );
+    }
+    header.push_str(
+        "\n\
+         #ifdef __cplusplus\n\
+         }\n\
+         #endif\n\
+         \n\
+         #endif\n",
+    );
+    Ok(header)
+}
@@ -0,0 +1,166 @@
+//! Subcommands that `#[command]` adds to a slice, and dispatching to them.
+
+use std::fmt::{self, Write};
+
+use crate::distributed_fn_slice::DistributedFnSlice;
+use crate::fn_pointer::Call;
+
+/// Returned by the element that `#[command]` adds for the function, or for
+/// every instantiation of a generic one.
+#[derive(Clone, Debug)]
+pub struct Command {
+    /// The `name` of the command, followed for a generic one by the type
+    /// argument in kebab case: `export-json` for `export::<Json>`.
+    pub name: String,
+    /// The doc comment of the function.
+    pub help: &'static str,
+    /// Called with the arguments that follow the subcommand, returning the
+    /// exit status.
+    pub run: fn(&[String]) -> i32,
+}
+
+impl Command {
+    /// The first paragraph of the help, on one line.
+    pub fn summary(&self) -> String {
+        let paragraph = self.help.split("\n\n").next().unwrap_or_default();
+        paragraph.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Two different functions registered as commands under the same name, such
+/// as a `name` given twice or a generic command whose type arguments have the
+/// same last path segment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clash {
+    /// The name of both commands.
+    pub name: String,
+}
+
+impl fmt::Display for Clash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "more than one command is named `{}`", self.name)
+    }
+}
+
+impl std::error::Error for Clash {}
+
+#[doc(hidden)]
+pub fn private_name(name: &str, type_name: Option<&str>) -> String {
+    match type_name {
+        Some(type_name) => format!("{}-{}", name, kebab_case(type_name)),
+        None => name.to_owned(),
+    }
+}
+
+// The last segment of the type's path without its generic arguments, with a
+// dash wherever a new word starts: `my_app::JsonLines<u8>` is `json-lines`.
+fn kebab_case(type_name: &str) -> String {
+    let base = type_name.split('<').next().unwrap_or_default();
+    let segment: Vec<char> = base
+        .rsplit("::")
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    let mut kebab = String::new();
+    for (i, &c) in segment.iter().enumerate() {
+        if c == '_' {
+            kebab.push('-');
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| segment[i]);
+        let next = segment.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && match prev {
+                Some(prev) if prev.is_lowercase() || prev.is_ascii_digit() => true,
+                Some(prev) if prev.is_uppercase() => matches!(next, Some(next) if next.is_lowercase()),
+                _ => false,
+            };
+        if starts_word && !kebab.ends_with('-') {
+            kebab.push('-');
+        }
+        kebab.extend(c.to_lowercase());
+    }
+    kebab
+}
+
+/// The commands in `commands` ordered by name. A command registered more
+/// than once, as when several crates link the same instantiation, is listed
+/// once. Different functions under the same name are a [`Clash`].
+pub fn list<F: Call<(), Output = Command> + 'static>(commands: &DistributedFnSlice<[F]>) -> Result<Vec<Command>, Clash> {
+    let mut list: Vec<Command> = commands.iter().map(|command| command.call(())).collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list.dedup_by(|a, b| a.name == b.name && a.run as usize == b.run as usize);
+    match list.windows(2).find(|pair| pair[0].name == pair[1].name) {
+        Some(pair) => Err(Clash { name: pair[0].name.clone() }),
+        None => Ok(list),
+    }
+}
+
+/// The usage message of `program`, listing the summary of every command.
+pub fn usage<F: Call<(), Output = Command> + 'static>(commands: &DistributedFnSlice<[F]>, program: &str) -> Result<String, Clash> {
+    Ok(usage_of(&list(commands)?, program))
+}
+
+fn usage_of(list: &[Command], program: &str) -> String {
+    let mut usage = format!("usage: {} <command> [args...]\n\ncommands:\n", program);
+    let width = list.iter().map(|command| command.name.len()).chain(Some("help".len())).max().unwrap();
+    for command in list {
+        let _ = writeln!(usage, "  {:<width$}  {}", command.name, command.summary());
+    }
+    let _ = writeln!(usage, "  {:<width$}  Print this message or the help of a command", "help");
+    usage
+}
+
+/// Runs the command named by `argv[1]` with the arguments after it, where
+/// `argv[0]` is the program as in `std::env::args`, and returns its exit
+/// status. `help`, `--help` and `-h` print the usage, or with a command
+/// after them its help, and return 0. A missing or unknown command prints
+/// the usage to standard error and returns 2, as does a [`Clash`], which is
+/// printed instead.
+pub fn dispatch<F: Call<(), Output = Command> + 'static>(commands: &DistributedFnSlice<[F]>, argv: &[String]) -> i32 {
+    let program = argv.first().map_or("command", String::as_str);
+    let list = match list(commands) {
+        Ok(list) => list,
+        Err(clash) => {
+            eprintln!("error: {}", clash);
+            return 2;
+        }
+    };
+    let usage = usage_of(&list, program);
+    let find = |name: &str| list.iter().find(|command| command.name == name);
+    match argv.get(1).map(String::as_str) {
+        None => {
+            eprint!("{}", usage);
+            2
+        }
+        Some("help" | "--help" | "-h") => match argv.get(2) {
+            None => {
+                print!("{}", usage);
+                0
+            }
+            Some(name) => match find(name) {
+                Some(command) => {
+                    println!("usage: {} {} [args...]", program, command.name);
+                    if !command.help.is_empty() {
+                        println!();
+                        println!("{}", command.help);
+                    }
+                    0
+                }
+                None => unknown(&usage, name),
+            },
+        },
+        Some(name) => match find(name) {
+            Some(command) => (command.run)(&argv[2..]),
+            None => unknown(&usage, name),
+        },
+    }
+}
+
+fn unknown(usage: &str, name: &str) -> i32 {
+    eprintln!("unknown command `{}`", name);
+    eprint!("{}", usage);
+    2
+}
@@ -0,0 +1,215 @@
+//! A dependency injection container over the providers that `#[provider]`
+//! adds to a slice, one for each type they return.
+//!
+//! A generic factory returning `Box<dyn Any>` instead provides its type
+//! parameter, one type for every instantiation, and has to box a value of
+//! it:
+//!
+//! ```ignore
+//! #[provider(PROVIDERS)]
+//! fn provide<T: Default + 'static>() -> Box<dyn Any> {
+//!     Box::new(T::default())
+//! }
+//! ```
+//!
+//! The attribute requires the parameter to be `Send + Sync`, as the values
+//! of every provider are.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::distributed_fn_slice::DistributedFnSlice;
+use crate::fn_pointer::Call;
+use crate::types::TypeInfo;
+
+/// Returned by the element that `#[provider]` adds for the function, or for
+/// every instantiation of a generic one.
+pub struct Provider {
+    /// The type it provides.
+    pub type_info: TypeInfo,
+    /// Whether the container creates the value once and hands out the same
+    /// one, rather than a new one on every resolution.
+    pub singleton: bool,
+    /// Creates the value, resolving its dependencies from the container.
+    pub create: fn(&Container) -> Arc<dyn Any + Send + Sync>,
+}
+
+/// Why a type could not be resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// No provider for the type is linked into the program.
+    Missing {
+        type_name: &'static str,
+        /// The types that have a provider, ordered by name.
+        available: Vec<&'static str>,
+    },
+    /// Providers that depend on each other: each resolves the next one, and
+    /// the last the first.
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::Missing { type_name, available } => {
+                write!(f, "no provider for `{}`", type_name)?;
+                match available.split_first() {
+                    None => write!(f, ", no types are provided"),
+                    Some((first, rest)) => {
+                        write!(f, ", available: `{}`", first)?;
+                        for type_name in rest {
+                            write!(f, ", `{}`", type_name)?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            ResolveError::Cycle(type_names) => {
+                write!(f, "cycle between providers: ")?;
+                for type_name in type_names {
+                    write!(f, "`{}` -> ", type_name)?;
+                }
+                write!(f, "`{}`", type_names[0])
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Two different providers of the same type, which leaves the container
+/// without a way to choose between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ambiguous {
+    /// The type both provide.
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for Ambiguous {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "more than one provider for `{}`", self.type_name)
+    }
+}
+
+impl std::error::Error for Ambiguous {}
+
+thread_local! {
+    // The types being resolved on this thread, innermost last, with the
+    // container resolving them.
+    static RESOLVING: RefCell<Vec<(usize, TypeId, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+// Takes the type off the stack of types being resolved, also when its
+// provider panics.
+struct Resolving;
+
+impl Drop for Resolving {
+    fn drop(&mut self) {
+        RESOLVING.with(|resolving| resolving.borrow_mut().pop());
+    }
+}
+
+/// The providers of a slice by the type they provide, which resolves values
+/// and keeps the singletons it created.
+pub struct Container {
+    providers: HashMap<TypeId, Provider>,
+    singletons: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl Container {
+    /// A container resolving types with the providers in `providers`. A
+    /// provider registered more than once, as when several crates link the
+    /// same instantiation, counts once. Different providers of the same type
+    /// are [`Ambiguous`].
+    pub fn new<F: Call<(), Output = Provider> + 'static>(providers: &DistributedFnSlice<[F]>) -> Result<Self, Ambiguous> {
+        let mut by_type = HashMap::new();
+        for provider in providers {
+            let provider = provider.call(());
+            match by_type.entry(provider.type_info.type_id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(provider);
+                }
+                Entry::Occupied(entry) => {
+                    let first = entry.get();
+                    if first.create as usize != provider.create as usize || first.singleton != provider.singleton {
+                        return Err(Ambiguous {
+                            type_name: provider.type_info.name,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(Container {
+            providers: by_type,
+            singletons: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The types that have a provider, ordered by name.
+    pub fn provided(&self) -> Vec<TypeInfo> {
+        let mut provided: Vec<TypeInfo> = self.providers.values().map(|provider| provider.type_info).collect();
+        provided.sort_by(|a, b| a.name.cmp(b.name));
+        provided
+    }
+
+    /// The value of type `T`: the singleton, created by its provider the
+    /// first time, or a new one from a transient provider. Providers resolve
+    /// their dependencies from the container they are given.
+    pub fn resolve<T: Send + Sync + 'static>(&self) -> Result<Arc<T>, ResolveError> {
+        let value = self.resolve_any(TypeId::of::<T>(), std::any::type_name::<T>())?;
+        Ok(value.downcast().unwrap())
+    }
+
+    /// [`resolve`](Self::resolve) for providers, which cannot return an
+    /// error: it panics with the reason `T` could not be resolved.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Arc<T> {
+        match self.resolve() {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn resolve_any(&self, type_id: TypeId, type_name: &'static str) -> Result<Arc<dyn Any + Send + Sync>, ResolveError> {
+        let provider = match self.providers.get(&type_id) {
+            Some(provider) => provider,
+            None => {
+                return Err(ResolveError::Missing {
+                    type_name,
+                    available: self.provided().iter().map(|info| info.name).collect(),
+                });
+            }
+        };
+        if provider.singleton {
+            if let Some(value) = self.singletons.lock().unwrap().get(&type_id) {
+                return Ok(value.clone());
+            }
+        }
+
+        let key = self as *const Container as usize;
+        RESOLVING.with(|resolving| {
+            let mut resolving = resolving.borrow_mut();
+            match resolving.iter().position(|&(container, id, _)| container == key && id == type_id) {
+                Some(start) => Err(ResolveError::Cycle(resolving[start..].iter().map(|&(_, _, name)| name).collect())),
+                None => {
+                    resolving.push((key, type_id, type_name));
+                    Ok(())
+                }
+            }
+        })?;
+        let value = {
+            let _resolving = Resolving;
+            (provider.create)(self)
+        };
+        if !provider.singleton {
+            return Ok(value);
+        }
+        // Another thread may have created it in the meantime; the first one
+        // stored is the singleton.
+        let mut singletons = self.singletons.lock().unwrap();
+        Ok(singletons.entry(type_id).or_insert(value).clone())
+    }
+}
@@ -0,0 +1,58 @@
+//! Calling the instantiation of a generic function of two arguments that
+//! `#[double_dispatch]` added to a slice for the dynamic types of two values.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::distributed_fn_slice::DistributedFnSlice;
+use crate::fn_pointer::Call;
+use crate::types::TypeInfo;
+
+/// Returned by the element that `#[double_dispatch]` adds for every
+/// instantiation of the function.
+pub struct Binary<R> {
+    /// The type of the first argument.
+    pub left: TypeInfo,
+    /// The type of the second argument.
+    pub right: TypeInfo,
+    /// Calls the instantiation with the two values, which have to be of the
+    /// types `left` and `right`.
+    pub call: fn(&dyn Any, &dyn Any) -> R,
+}
+
+/// The instantiations of a slice by the types of their two arguments.
+pub type Table<R> = HashMap<(TypeId, TypeId), Binary<R>>;
+
+// The table of each slice, as a `Table<R>` of its return type, built on first
+// use.
+static TABLES: Lazy<Mutex<HashMap<usize, &'static (dyn Any + Send + Sync)>>> = Lazy::new(Default::default);
+
+/// The instantiations in `table`, keyed by the `TypeId`s of their two type
+/// arguments. An instantiation linked by more than one crate is in it once.
+pub fn table<R: 'static, F: Call<(), Output = Binary<R>> + 'static>(table: &'static DistributedFnSlice<[F]>) -> &'static Table<R> {
+    let key = table as *const DistributedFnSlice<[F]> as usize;
+    let built = *TABLES.lock().unwrap().entry(key).or_insert_with(|| {
+        let mut built = Table::<R>::new();
+        for element in table {
+            let binary = element.call(());
+            built.entry((binary.left.type_id, binary.right.type_id)).or_insert(binary);
+        }
+        Box::leak(Box::new(built))
+    });
+    built.downcast_ref().unwrap()
+}
+
+/// Calls the instantiation in `table` for the types of `a` and `b`, in that
+/// order. `None` if no instantiation for them is linked into the program,
+/// including one for the types the other way around.
+pub fn dispatch2<R: 'static, F: Call<(), Output = Binary<R>> + 'static>(
+    table: &'static DistributedFnSlice<[F]>,
+    a: &dyn Any,
+    b: &dyn Any,
+) -> Option<R> {
+    let binary = self::table(table).get(&(a.type_id(), b.type_id()))?;
+    Some((binary.call)(a, b))
+}
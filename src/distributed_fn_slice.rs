@@ -1,19 +1,36 @@
+use core::fmt;
 use core::mem;
-use std::fmt::Write;
 use core::ops::Deref;
 use core::slice;
 use once_cell::sync::OnceCell;
-use capstone::prelude::*;
 
-use crate::__private::Slice;
+use crate::__private::{Declaration, Key, Metadata, Slice};
+use crate::report::DisassemblyReport;
+use crate::table::Table;
+#[cfg(feature = "symtab")]
+use crate::elf::{Image, Section, Symbol};
+
+// The address of an element and those of the metadata and key functions its
+// bodies call, if any.
+type Extracted = (usize, Option<usize>, Option<usize>);
+
+// The address of an element, the record of the metadata function its bodies
+// call and the type arguments of its instantiation, if any.
+type Located = (usize, Option<&'static Metadata>, Option<&'static str>);
 
 pub struct DistributedFnSlice<T: ?Sized + Slice + 'static> {
     name: &'static str,
     section_start: *const u8,
     section_stop: *const u8,
-    dupcheck_start: *const usize,
-    dupcheck_stop: *const usize,
-    slice: OnceCell<&'static T>,
+    dupcheck_start: *const Declaration,
+    dupcheck_stop: *const Declaration,
+    metadata_start: *const Metadata,
+    metadata_stop: *const Metadata,
+    table: Option<&'static Table>,
+    // Calls the element at an address for its value, for slices of trait
+    // objects. Slices of function pointers hold the addresses themselves.
+    object: Option<unsafe fn(usize) -> T::Element>,
+    slice: OnceCell<(&'static T, &'static [Located])>,
 }
 
 unsafe impl<T: ?Sized + Slice> Send for DistributedFnSlice<T> {}
@@ -28,6 +45,10 @@ impl<T: ?Sized + Slice> Clone for DistributedFnSlice<T> {
             section_stop: self.section_stop,
             dupcheck_start: self.dupcheck_start,
             dupcheck_stop: self.dupcheck_stop,
+            metadata_start: self.metadata_start,
+            metadata_stop: self.metadata_stop,
+            table: self.table,
+            object: self.object,
             slice: self.slice.clone(),
         }
     }
@@ -35,6 +56,7 @@ impl<T: ?Sized + Slice> Clone for DistributedFnSlice<T> {
 
 impl<T> DistributedFnSlice<[T]> {
     #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
     #[cfg(any(
         target_os = "none",
         target_os = "linux",
@@ -48,8 +70,12 @@ impl<T> DistributedFnSlice<[T]> {
         name: &'static str,
         section_start: *const u8,
         section_stop: *const u8,
-        dupcheck_start: *const usize,
-        dupcheck_stop: *const usize,
+        dupcheck_start: *const Declaration,
+        dupcheck_stop: *const Declaration,
+        metadata_start: *const Metadata,
+        metadata_stop: *const Metadata,
+        table: Option<&'static Table>,
+        object: Option<unsafe fn(usize) -> T>,
     ) -> Self {
         DistributedFnSlice {
             name,
@@ -57,11 +83,16 @@ impl<T> DistributedFnSlice<[T]> {
             section_stop,
             dupcheck_start,
             dupcheck_stop,
+            metadata_start,
+            metadata_stop,
+            table,
+            object,
             slice: OnceCell::new(),
         }
     }
 
     #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
     #[cfg(target_os = "windows")]
     pub const unsafe fn private_new(
         name: &'static str,
@@ -69,22 +100,24 @@ impl<T> DistributedFnSlice<[T]> {
         section_stop: *const [u8; 0],
         dupcheck_start: *const (),
         dupcheck_stop: *const (),
+        metadata_start: *const (),
+        metadata_stop: *const (),
+        table: Option<&'static Table>,
+        object: Option<unsafe fn(usize) -> T>,
     ) -> Self {
         DistributedFnSlice {
             name,
             section_start: section_start as *const u8,
             section_stop: section_stop as *const u8,
-            dupcheck_start: dupcheck_start as *const usize,
-            dupcheck_stop: dupcheck_stop as *const usize,
+            dupcheck_start: dupcheck_start as *const Declaration,
+            dupcheck_stop: dupcheck_stop as *const Declaration,
+            metadata_start: metadata_start as *const Metadata,
+            metadata_stop: metadata_stop as *const Metadata,
+            table,
+            object,
             slice: OnceCell::new(),
         }
     }
-
-    #[doc(hidden)]
-    #[inline]
-    pub unsafe fn private_typecheck(&self, element: T) {
-        mem::forget(element);
-    }
 }
 
 impl<T> DistributedFnSlice<[T]> {
@@ -93,33 +126,172 @@ impl<T> DistributedFnSlice<[T]> {
         unsafe { slice::from_raw_parts(self.section_start, len) }
     }
 
+    // Every declaration linked into the program that uses this slice's name.
+    fn declarations(&self) -> &'static [Declaration] {
+        // On Windows the start marker is not aligned for the records.
+        let align = mem::align_of::<Declaration>();
+        let start = (self.dupcheck_start as usize + align - 1) & !(align - 1);
+        let len = (self.dupcheck_stop as usize).saturating_sub(start) / mem::size_of::<Declaration>();
+        unsafe { slice::from_raw_parts(start as *const Declaration, len) }
+    }
+
+    // The metadata records of the elements of this slice.
+    fn records(&self) -> &'static [Metadata] {
+        // On Windows the start marker is not aligned for the records.
+        let align = mem::align_of::<Metadata>();
+        let start = (self.metadata_start as usize + align - 1) & !(align - 1);
+        let len = (self.metadata_stop as usize).saturating_sub(start) / mem::size_of::<Metadata>();
+        unsafe { slice::from_raw_parts(start as *const Metadata, len) }
+    }
+
     pub fn static_slice(&self) -> &'static [T] {
-        if self.dupcheck_start.wrapping_add(1) < self.dupcheck_stop {
-            panic!("duplicate #[distributed_slice] with name \"{}\"", self.name);
-        }
-
-        match self.slice.get() {
-            Some(slice) => slice,
-            None => {
-
-                let res: &'static [T] = Box::leak(extract_function_pointers::<T>(self.get_code()).into_boxed_slice());
-                match self.slice.set(res) {
-                    Ok(()) => res,
-                    Err(res) => {
-                        unsafe {
-                            std::mem::drop(Box::<[T]>::from_raw(
-                                std::mem::transmute::<*const [T], *mut [T]>(res as *const [T])
-                            ))
-                        }
-                        self.slice.get().unwrap()
-                    },
-                }
+        let declarations = self.declarations();
+        if declarations.len() > 1 {
+            panic!("{}", duplicate_message(self.name, declarations));
+        }
+
+        self.elements_with_metadata().0
+    }
+
+    // Elements with a `pos` come first, in ascending order, followed by the
+    // others in section order.
+    fn elements_with_metadata(&self) -> (&'static [T], &'static [Located]) {
+        *self.slice.get_or_init(|| {
+            let elements = self.elements(Extraction::Table)
+                .or_else(|| self.elements(Extraction::Symbols))
+                .or_else(|| self.elements(Extraction::Disassembly))
+                .unwrap_or_else(|| panic!(
+                    "cannot locate the elements of \"{}\": distributed_fn_slice has no \
+                     disassembler for this target and generic-linkme-postlink has not run on a \
+                     build with the `postlink` feature",
+                    self.name,
+                ));
+            // A call into the section that is not to the function of a record
+            // is not a metadata function, and is left alone, as is the call
+            // after it, which is only known to be to a key function then.
+            let records = self.records();
+            let mut elements: Vec<Located> = elements.into_iter()
+                .map(|(address, metadata, key)| {
+                    let metadata = metadata.and_then(|metadata| {
+                        records.iter().find(|record| record.function as usize == metadata)
+                    });
+                    let type_args = key.filter(|_| metadata.is_some()).map(|key| {
+                        let key = unsafe { mem::transmute::<usize, extern "C" fn() -> Key>(key) };
+                        &*Box::leak(key()().into_boxed_str())
+                    });
+                    (address, metadata, type_args)
+                })
+                .collect();
+            elements.sort_by_key(|(_, metadata, _)| match metadata.and_then(Metadata::pos) {
+                Some(pos) => (0, pos),
+                None => (1, 0),
+            });
+            let addresses: Vec<usize> = elements.iter().map(|(address, _, _)| *address).collect();
+            let slice: Vec<T> = match self.object {
+                Some(object) => addresses.into_iter().map(|address| unsafe { object(address) }).collect(),
+                None => function_pointers::<T>(addresses),
+            };
+            (Box::leak(slice.into_boxed_slice()), Box::leak(elements.into_boxed_slice()))
+        })
+    }
+
+    /// Addresses of the elements as located by `extraction`, in section
+    /// order, or `None` if that method is not available in this build.
+    pub fn addresses(&self, extraction: Extraction) -> Option<Vec<usize>> {
+        let elements = self.elements(extraction)?;
+        Some(elements.into_iter().map(|(address, _, _)| address).collect())
+    }
+
+    // The elements with the metadata and key functions their bodies call.
+    fn elements(&self, extraction: Extraction) -> Option<Vec<Extracted>> {
+        match extraction {
+            Extraction::Disassembly => disassembled_elements(self.get_code()),
+            Extraction::Symbols => self.symbol_elements(),
+            Extraction::Table => {
+                let entries = self.table?.entries()?;
+                let start = self.section_start as isize;
+                let address = |offset: isize| (start + offset) as usize;
+                Some(entries.into_iter()
+                    .map(|(offset, metadata, key)| (address(offset), metadata.map(address), key.map(address)))
+                    .collect())
             }
         }
     }
 
+    /// The element at `index` with its address, symbol, name and position,
+    /// for diagnostics. `None` if `index` is out of bounds.
+    ///
+    /// Panics like [`static_slice`](Self::static_slice) if the slice is
+    /// declared more than once.
+    pub fn describe(&self, index: usize) -> Option<Entry> {
+        self.static_slice();
+        let (_, elements) = self.elements_with_metadata();
+        let (address, metadata, type_args) = *elements.get(index)?;
+        Some(Entry {
+            index,
+            address,
+            symbol: self.symbol_name(address),
+            type_args,
+            name: metadata.and_then(|metadata| metadata.name),
+            pos: metadata.and_then(Metadata::pos),
+            after: metadata.map_or(&[], |metadata| metadata.after),
+            before: metadata.map_or(&[], |metadata| metadata.before),
+        })
+    }
+
+    // The image the section was loaded from and the section in it.
+    #[cfg(feature = "symtab")]
+    fn image(&self) -> Option<(&'static Image, &'static Section)> {
+        let image = Image::containing(self.section_start as usize)?;
+        let section = image.section(&format!("generic_linkme_{}", self.name))
+            .or_else(|| image.section(&format!("set_generic_linkme_{}", self.name)))?;
+        Some((image, section))
+    }
+
+    #[cfg(feature = "symtab")]
+    fn symbol_elements(&self) -> Option<Vec<Extracted>> {
+        let (image, section) = self.image()?;
+        symbol_targets(image, section, self.section_start as usize)
+    }
+
+    #[cfg(not(feature = "symtab"))]
+    fn symbol_elements(&self) -> Option<Vec<Extracted>> {
+        None
+    }
+
+    #[cfg(feature = "symtab")]
+    fn symbol_name(&self, address: usize) -> Option<String> {
+        let (image, section) = self.image()?;
+        let bias = (self.section_start as u64).wrapping_sub(section.address);
+        let address = (address as u64).wrapping_sub(bias);
+        let symbol = image.symbol_at(address)?;
+        match address - symbol.address {
+            0 => Some(symbol.demangled()),
+            offset => Some(format!("{}+{:#x}", symbol.demangled(), offset)),
+        }
+    }
+
+    #[cfg(not(feature = "symtab"))]
+    fn symbol_name(&self, _address: usize) -> Option<String> {
+        None
+    }
+
+    /// Describes how disassembly locates the elements: the bodies found in
+    /// the section, their instructions and call targets and which target was
+    /// taken for each. `None` if this build has no disassembler for the
+    /// target.
+    pub fn report(&self) -> Option<DisassemblyReport> {
+        let code = self.get_code();
+        let bodies = Arch::HOST?.bodies(code, code.as_ptr() as u64, &peek)?;
+        Some(DisassemblyReport::new(self.name, code, &bodies))
+    }
+
+    /// [`report`](Self::report) as text.
     pub fn debug_string(&self) -> String {
-        disasm(self.get_code())
+        match self.report() {
+            Some(report) => report.to_string(),
+            None => format!("{}: no disassembler for this target", self.name),
+        }
     }
 }
 
@@ -138,72 +310,725 @@ impl<T: 'static> IntoIterator for &DistributedFnSlice<[T]> {
     }
 }
 
-fn extract_function_pointers<T>(code: &[u8]) -> Vec<T> {
-    let cs = Capstone::new()
-        .x86()
-        .mode(arch::x86::ArchMode::Mode64)
-        .syntax(arch::x86::ArchSyntax::Att)
-        .detail(true)
-        .build()
-        .expect("Failed to create Capstone object");
-    let addr = code.as_ptr() as usize;
-    let insns = cs.disasm_all(code, addr as u64)
-        .expect("Failed to disassemble");
-    let mut v = Vec::new();
-    for i in insns.as_ref() {
-        let Some(name) = cs.insn_name(i.id()) else { continue };
-        if name == "call" || name == "jmp" {
-            let detail: InsnDetail = cs.insn_detail(&i).expect("Failed to get insn detail");
-            let arch_detail: ArchDetail = detail.arch_detail();
-            let ops = arch_detail.operands();
-            for op in ops {
-                let op = match op {
-                    arch::ArchOperand::X86Operand(op) => op,
-                    _ => continue,
-                };
-                match op.op_type {
-                    arch::x86::X86OperandType::Imm(val) => {
-                        assert!(std::mem::size_of::<T>() == std::mem::size_of::<i64>());
-                        let f = unsafe { std::mem::transmute_copy(&val) };
-                        v.push(f);
-                    },
-                    _ => continue,
+/// How the elements of a [`DistributedFnSlice`] are located in its section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extraction {
+    /// Disassemble the element bodies and follow their calls. Requires the
+    /// `disasm` feature on x86 and x86_64.
+    Disassembly,
+    /// Look the element bodies and their targets up in the ELF symbol tables
+    /// of the running executable or shared object. Requires the `symtab`
+    /// feature and a build whose symbols have not been stripped. When
+    /// available it is preferred over disassembly.
+    Symbols,
+    /// Read the offsets that the `generic-linkme-postlink` tool has stored in
+    /// the slice's table after linking. Requires the `postlink` feature, which
+    /// reserves the table. Preferred over both of the above when the tool has
+    /// run on the executable.
+    Table,
+}
+
+/// An element of a [`DistributedFnSlice`], as returned by
+/// [`describe`](DistributedFnSlice::describe).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    index: usize,
+    address: usize,
+    symbol: Option<String>,
+    type_args: Option<&'static str>,
+    name: Option<&'static str>,
+    pos: Option<usize>,
+    after: &'static [&'static str],
+    before: &'static [&'static str],
+}
+
+impl Entry {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The function pointer as an address.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Demangled name of the function, looked up in the ELF symbol tables of
+    /// the running program. `None` without the `symtab` feature or when the
+    /// symbols have been stripped.
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    /// The type arguments of this instantiation of a generic element with
+    /// metadata, as [`type_name`](core::any::type_name) gives them, separated
+    /// by commas. `None` for other elements.
+    pub fn type_args(&self) -> Option<&'static str> {
+        self.type_args
+    }
+
+    /// The `name` given in the element's attribute.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// The `pos` given in the element's attribute.
+    pub fn pos(&self) -> Option<usize> {
+        self.pos
+    }
+
+    /// The names given in the element's `after` argument.
+    pub fn after(&self) -> &'static [&'static str] {
+        self.after
+    }
+
+    /// The names given in the element's `before` argument.
+    pub fn before(&self) -> &'static [&'static str] {
+        self.before
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = self.symbol().unwrap_or("unknown");
+        write!(f, "[{}] {:#x} {}", self.index, self.address, symbol)?;
+        match (self.name, self.pos) {
+            (Some(name), Some(pos)) => write!(f, " ({:?}, pos {})", name, pos),
+            (Some(name), None) => write!(f, " ({:?})", name),
+            (None, Some(pos)) => write!(f, " (pos {})", pos),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+fn duplicate_message(name: &str, declarations: &[Declaration]) -> String {
+    let mut message = format!("duplicate #[distributed_fn_slice] with name \"{}\", declared", name);
+    for declaration in declarations {
+        let krate = declaration.module_path.split("::").next().unwrap_or_default();
+        message += &format!(
+            "\n  in crate `{}`, module `{}`, at {}:{}",
+            krate, declaration.module_path, declaration.file, declaration.line,
+        );
+    }
+    message
+}
+
+// `T` is a function pointer type, which the declaration writes out or checks
+// the size and alignment of for `FnPointer` types.
+fn function_pointers<T>(addresses: Vec<usize>) -> Vec<T> {
+    addresses.into_iter()
+        .map(|address| unsafe { mem::transmute_copy(&address) })
+        .collect()
+}
+
+fn disassembled_elements(code: &[u8]) -> Option<Vec<Extracted>> {
+    let elements = Arch::HOST?.elements(code, code.as_ptr() as u64, &peek)?;
+    Some(elements.into_iter()
+        .map(|(target, metadata, key)| (target as usize, metadata.map(|f| f as usize), key.map(|f| f as usize)))
+        .collect())
+}
+
+fn peek(addr: u64) -> Option<[u8; 4]> {
+    Some(unsafe { (addr as usize as *const [u8; 4]).read_unaligned() })
+}
+
+// Every function symbol inside the section is either the body of one element
+// instantiation, `path::f_generic_linkme_impl`, whose target is the nested
+// `path::f_generic_linkme_impl::f_middle_impl` of the same instantiation, the
+// metadata function `path::f_generic_linkme_impl::f_generic_linkme_meta`
+// that the bodies of an element with a name or position call, or the key
+// function `path::f_generic_linkme_impl::f_generic_linkme_key` of one
+// instantiation of such an element.
+#[cfg(feature = "symtab")]
+fn symbol_targets(image: &Image, section: &Section, runtime_start: usize) -> Option<Vec<Extracted>> {
+    let bias = (runtime_start as u64).wrapping_sub(section.address);
+    let bodies = image.symbols_in(section.address, section.address + section.size);
+    if bodies.is_empty() && section.size != 0 {
+        return None;
+    }
+    bodies.iter()
+        .map(|body| (body, body.demangled()))
+        .filter(|(_, name)| {
+            let (path, _) = split_generic_args(name);
+            !path.ends_with("_generic_linkme_meta") && !path.ends_with("_generic_linkme_key")
+        })
+        .map(|(body, name)| {
+            let metadata = image.symbols_named(&metadata_name(&name)?)
+                .next()
+                .map(|symbol| symbol.address.wrapping_add(bias) as usize);
+            let target = called(image, body, bias, &middle_impl_name(&name)?)?;
+            let key = match metadata {
+                Some(_) => called(image, body, bias, &key_name(&name)?),
+                None => None,
+            };
+            Some((target, metadata, key))
+        })
+        .collect()
+}
+
+// The instantiation of the function `name` that `body` calls.
+#[cfg(feature = "symtab")]
+fn called(image: &Image, body: &Symbol, bias: u64, name: &str) -> Option<usize> {
+    let candidates: Vec<usize> = image.symbols_named(name)
+        .map(|symbol| symbol.address.wrapping_add(bias) as usize)
+        .collect();
+    if let [target] = candidates[..] {
+        return Some(target);
+    }
+    // Legacy mangling leaves the generic arguments out of symbol names, so
+    // instantiations of one element share a name. The call in the body
+    // tells them apart.
+    let code = unsafe {
+        slice::from_raw_parts(body.address.wrapping_add(bias) as usize as *const u8, body.size as usize)
+    };
+    let targets = Arch::HOST?.call_targets(code, code.as_ptr() as u64, &peek)?;
+    let mut chosen = candidates.into_iter().filter(|&candidate| targets.contains(&(candidate as u64)));
+    match (chosen.next(), chosen.next()) {
+        (Some(target), None) => Some(target),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "symtab")]
+fn middle_impl_name(outer_impl: &str) -> Option<String> {
+    let (path, generic_args) = split_generic_args(outer_impl);
+    let stem = path.rsplit("::").next()?.strip_suffix("_generic_linkme_impl")?;
+    Some(format!("{}::{}_middle_impl{}", path, stem, generic_args))
+}
+
+#[cfg(feature = "symtab")]
+fn key_name(outer_impl: &str) -> Option<String> {
+    let (path, generic_args) = split_generic_args(outer_impl);
+    let stem = path.rsplit("::").next()?.strip_suffix("_generic_linkme_impl")?;
+    Some(format!("{}::{}_generic_linkme_key{}", path, stem, generic_args))
+}
+
+// The metadata function is not generic, so its name has no generic arguments
+// with either mangling scheme.
+#[cfg(feature = "symtab")]
+fn metadata_name(outer_impl: &str) -> Option<String> {
+    let (path, _) = split_generic_args(outer_impl);
+    let stem = path.rsplit("::").next()?.strip_suffix("_generic_linkme_impl")?;
+    Some(format!("{}::{}_generic_linkme_meta", path, stem))
+}
+
+// Splits a trailing `::<...>`, present with the v0 mangling scheme, off a
+// demangled path.
+#[cfg(feature = "symtab")]
+fn split_generic_args(name: &str) -> (&str, &str) {
+    if !name.ends_with('>') {
+        return (name, "");
+    }
+    let mut depth = 0;
+    for (i, c) in name.char_indices().rev() {
+        match c {
+            '>' if !name[..i].ends_with('-') => depth += 1,
+            '<' => {
+                depth -= 1;
+                if depth == 0 {
+                    if name[..i].ends_with("::") {
+                        return (&name[..i - 2], &name[i - 2..]);
+                    }
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    (name, "")
+}
+
+// The target of an element body and the metadata and key functions it calls.
+pub(crate) type BodyTargets = (u64, Option<u64>, Option<u64>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Arch {
+    #[cfg_attr(not(any(target_arch = "x86", feature = "symtab")), allow(dead_code))]
+    X86,
+    X86_64,
+    #[cfg_attr(not(any(target_arch = "riscv64", feature = "symtab")), allow(dead_code))]
+    RiscV64,
+}
+
+// One decoded instruction, reduced to what element extraction needs.
+pub(crate) struct Insn {
+    pub(crate) address: u64,
+    pub(crate) len: usize,
+    pub(crate) text: String,
+    /// Absolute target of a direct call or jump.
+    pub(crate) target: Option<u64>,
+    pub(crate) flow: Flow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Flow {
+    /// Execution may continue with the next instruction.
+    Next,
+    /// Returns or jumps away for good, ending the body.
+    End,
+    /// Alignment filler between bodies.
+    Padding,
+}
+
+impl Arch {
+    #[cfg(target_arch = "x86")]
+    pub(crate) const HOST: Option<Arch> = Some(Arch::X86);
+
+    #[cfg(target_arch = "x86_64")]
+    pub(crate) const HOST: Option<Arch> = Some(Arch::X86_64);
+
+    #[cfg(target_arch = "riscv64")]
+    pub(crate) const HOST: Option<Arch> = Some(Arch::RiscV64);
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64")))]
+    pub(crate) const HOST: Option<Arch> = None;
+
+    /// Decodes `code`, which is mapped at `addr`. `peek` reads the first
+    /// bytes of a call target and is used to skip the pc-relative addressing
+    /// helpers of 32-bit PIC code. `None` if this build has no decoder for
+    /// the architecture.
+    pub(crate) fn decode(
+        self,
+        code: &[u8],
+        addr: u64,
+        peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<Insn>> {
+        let mut insns = match self {
+            Arch::X86 | Arch::X86_64 => x86::decode(self, code, addr, peek)?,
+            Arch::RiscV64 => riscv::decode(code, addr),
+        };
+        // Bodies never jump to one another, so forward jumps within `code`
+        // are branches inside a body, which unoptimized code has e.g. around
+        // calls that cannot return.
+        let end = addr + code.len() as u64;
+        for insn in &mut insns {
+            if insn.flow == Flow::End && matches!(insn.target, Some(target) if target > insn.address && target < end) {
+                insn.flow = Flow::Next;
+                insn.target = None;
+            }
+        }
+        Some(insns)
+    }
+
+    /// Absolute targets of all direct calls and jumps in `code`.
+    #[cfg_attr(not(feature = "symtab"), allow(dead_code))]
+    pub(crate) fn call_targets(
+        self,
+        code: &[u8],
+        addr: u64,
+        peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<u64>> {
+        let insns = self.decode(code, addr, peek)?;
+        Some(insns.into_iter().filter_map(|insn| insn.target).collect())
+    }
+
+    /// Splits `code` into function bodies, each running up to a return or
+    /// tail jump, with the padding between them left out.
+    pub(crate) fn bodies(
+        self,
+        code: &[u8],
+        addr: u64,
+        peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<Vec<Insn>>> {
+        let mut bodies = Vec::new();
+        let mut body = Vec::new();
+        for insn in self.decode(code, addr, peek)? {
+            match insn.flow {
+                Flow::Padding if body.is_empty() => {}
+                Flow::End => {
+                    body.push(insn);
+                    bodies.push(mem::take(&mut body));
                 }
+                _ => body.push(insn),
             }
         }
+        if !body.is_empty() {
+            bodies.push(body);
+        }
+        Some(bodies)
+    }
+
+    /// The target of every element body in `code`: its last direct call or
+    /// jump out of `code`. Calls before it are made by the body itself, e.g.
+    /// to `memcpy` for arguments passed by value. Paired with the metadata
+    /// function the body calls and the key function it calls next, the only
+    /// possible targets inside `code`.
+    pub(crate) fn elements(
+        self,
+        code: &[u8],
+        addr: u64,
+        peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<BodyTargets>> {
+        let inside = addr..addr + code.len() as u64;
+        let bodies = self.bodies(code, addr, peek)?;
+        Some(bodies.iter()
+            .filter_map(|body| {
+                let targets = body.iter().filter_map(|insn| insn.target);
+                let target = targets.clone().rev().find(|target| !inside.contains(target))?;
+                let mut calls = targets.filter(|target| inside.contains(target));
+                let metadata = calls.next();
+                Some((target, metadata, calls.next()))
+            })
+            .collect())
     }
-    v
 }
 
-fn disasm(code: &[u8]) -> String {
-    let mut res = String::new();
-    let cs = Capstone::new()
-        .x86()
-        .mode(arch::x86::ArchMode::Mode64)
-        .syntax(arch::x86::ArchSyntax::Att)
-        .detail(true)
-        .build()
-        .expect("Failed to create Capstone object");
+// x86 and x86_64 go through capstone, which is optional so that programs
+// whose tables are filled in after linking do not have to ship it.
+#[cfg(feature = "disasm")]
+mod x86 {
+    use capstone::prelude::*;
 
-    write!(&mut res, "Code len = {}", code.len()).unwrap();
-    let addr = code.as_ptr() as usize;
-    let insns = cs.disasm_all(code, addr as u64)
-        .expect("Failed to disassemble");
-    write!(&mut res, "Found {} instructions", insns.len()).unwrap();
-    for i in insns.as_ref() {
-        write!(&mut res, "").unwrap();
-        write!(&mut res, "{}", i).unwrap();
-        write!(&mut res, "{:4}ins_id: {}", "", i.id().0).unwrap();
-        write!(&mut res, "{:4}ins_name: {}", "", cs.insn_name(i.id()).expect("failed to get insn name")).unwrap();
+    use super::{Arch, Flow, Insn};
 
-        let detail: InsnDetail = cs.insn_detail(&i).expect("Failed to get insn detail");
-        let arch_detail: ArchDetail = detail.arch_detail();
-        let ops = arch_detail.operands();
+    fn capstone(arch: Arch) -> Capstone {
+        let mode = match arch {
+            Arch::X86 => arch::x86::ArchMode::Mode32,
+            _ => arch::x86::ArchMode::Mode64,
+        };
+        Capstone::new()
+            .x86()
+            .mode(mode)
+            .syntax(arch::x86::ArchSyntax::Att)
+            .detail(true)
+            .build()
+            .expect("Failed to create Capstone object")
+    }
+
+    pub(super) fn decode(
+        arch: Arch,
+        code: &[u8],
+        addr: u64,
+        peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<Insn>> {
+        let cs = capstone(arch);
+        let insns = cs.disasm_all(code, addr)
+            .expect("Failed to disassemble");
+        let mut v = Vec::new();
+        for i in insns.as_ref() {
+            let name = cs.insn_name(i.id()).unwrap_or_default();
+            let mut target = None;
+            if name == "call" || name == "jmp" {
+                let detail: InsnDetail = cs.insn_detail(i).expect("Failed to get insn detail");
+                let arch_detail: ArchDetail = detail.arch_detail();
+                for op in arch_detail.operands() {
+                    let op = match op {
+                        arch::ArchOperand::X86Operand(op) => op,
+                        _ => continue,
+                    };
+                    if let arch::x86::X86OperandType::Imm(val) = op.op_type {
+                        let next = i.address() + i.bytes().len() as u64;
+                        if arch != Arch::X86 || !is_pc_thunk(next, val as u64, peek) {
+                            target = Some(val as u64);
+                        }
+                    }
+                }
+            }
+            let flow = match &*name {
+                "ret" | "jmp" | "ud2" => Flow::End,
+                "nop" | "int3" => Flow::Padding,
+                _ if i.bytes().iter().all(|&b| b == 0) => Flow::Padding,
+                _ => Flow::Next,
+            };
+            let text = format!("{} {}", i.mnemonic().unwrap_or_default(), i.op_str().unwrap_or_default());
+            v.push(Insn {
+                address: i.address(),
+                len: i.bytes().len(),
+                text: text.trim_end().to_owned(),
+                target,
+                flow,
+            });
+        }
+        Some(v)
+    }
 
-        write!(&mut res, "{:4}operands: {}", "", ops.len()).unwrap();
-        for op in ops {
-            write!(&mut res, "{:8}{:?}", "", op).unwrap();
+    // 32-bit PIC code loads its own address either with `call 1f; 1: pop %reg`
+    // or by calling a `__x86.get_pc_thunk.<reg>` helper, which is a single
+    // `mov (%esp), %reg; ret`. Neither call is an element.
+    fn is_pc_thunk(next: u64, target: u64, peek: &dyn Fn(u64) -> Option<[u8; 4]>) -> bool {
+        if target == next {
+            return true;
+        }
+        match peek(target) {
+            Some([0x8b, modrm, 0x24, 0xc3]) => modrm & 0xc7 == 0x04,
+            _ => false,
         }
     }
-    res
+}
+
+#[cfg(not(feature = "disasm"))]
+mod x86 {
+    use super::{Arch, Insn};
+
+    pub(super) fn decode(
+        _arch: Arch,
+        _code: &[u8],
+        _addr: u64,
+        _peek: &dyn Fn(u64) -> Option<[u8; 4]>,
+    ) -> Option<Vec<Insn>> {
+        None
+    }
+}
+
+// RISC-V is decoded by hand: element bodies end in either a `jal`/`c.j` with
+// a pc-relative target, or, for targets out of `jal` range, the
+// `auipc`+`jalr` pair LLVM emits for `call`/`tail`, whose target is the sum of
+// both immediates.
+mod riscv {
+    use super::{Flow, Insn as Decoded};
+
+    const OPCODE_AUIPC: u32 = 0x17;
+    const OPCODE_JAL: u32 = 0x6f;
+    const OPCODE_JALR: u32 = 0x67;
+    const NOP: u32 = 0x13;
+
+    enum Insn {
+        Jal { rd: u32, target: u64 },
+        Auipc { rd: u32, value: u64 },
+        Jalr { rd: u32, rs1: u32, offset: i64 },
+        Nop,
+        Other,
+    }
+
+    // Standard instructions have their two lowest bits set, anything else is
+    // a 16-bit compressed instruction.
+    pub(super) fn decode(code: &[u8], addr: u64) -> Vec<Decoded> {
+        let mut v = Vec::new();
+        let mut pending = None;
+        let mut offset = 0;
+        while offset + 2 <= code.len() {
+            let pc = addr + offset as u64;
+            let low = u16::from_le_bytes([code[offset], code[offset + 1]]);
+            let (len, word, insn) = if low & 0b11 != 0b11 {
+                (2, u32::from(low), decode_compressed(low, pc))
+            } else if offset + 4 <= code.len() {
+                let word = u32::from_le_bytes([code[offset], code[offset + 1], code[offset + 2], code[offset + 3]]);
+                (4, word, decode_standard(word, pc))
+            } else {
+                break;
+            };
+            offset += len;
+
+            let (text, target, flow) = match insn {
+                Insn::Jal { rd, target } => {
+                    let flow = if rd == 0 { Flow::End } else { Flow::Next };
+                    (format!("jal x{}, {:#x}", rd, target), Some(target), flow)
+                }
+                Insn::Auipc { rd, value } => {
+                    pending = Some((rd, value));
+                    v.push(Decoded {
+                        address: pc,
+                        len,
+                        text: format!("auipc x{}, {:#x}", rd, value),
+                        target: None,
+                        flow: Flow::Next,
+                    });
+                    continue;
+                }
+                Insn::Jalr { rd, rs1, offset } => {
+                    let target = match pending {
+                        Some((auipc_rd, value)) if auipc_rd == rs1 => Some(value.wrapping_add(offset as u64)),
+                        _ => None,
+                    };
+                    let flow = if rd == 0 { Flow::End } else { Flow::Next };
+                    (format!("jalr x{}, {}(x{})", rd, offset, rs1), target, flow)
+                }
+                Insn::Nop => ("nop".to_owned(), None, Flow::Padding),
+                Insn::Other if word == 0 => ("unimp".to_owned(), None, Flow::Padding),
+                Insn::Other if len == 2 => (format!(".half {:#06x}", word), None, Flow::Next),
+                Insn::Other => (format!(".word {:#010x}", word), None, Flow::Next),
+            };
+            pending = None;
+            v.push(Decoded { address: pc, len, text, target, flow });
+        }
+        v
+    }
+
+    fn decode_standard(insn: u32, pc: u64) -> Insn {
+        let rd = (insn >> 7) & 0x1f;
+        let rs1 = (insn >> 15) & 0x1f;
+        let funct3 = (insn >> 12) & 0x7;
+        match insn & 0x7f {
+            _ if insn == NOP => Insn::Nop,
+            OPCODE_JAL => {
+                let imm = (insn >> 31) << 20
+                    | ((insn >> 21) & 0x3ff) << 1
+                    | ((insn >> 20) & 0x1) << 11
+                    | ((insn >> 12) & 0xff) << 12;
+                Insn::Jal { rd, target: pc.wrapping_add(sign_extend(imm, 21) as u64) }
+            }
+            OPCODE_AUIPC => Insn::Auipc {
+                rd,
+                value: pc.wrapping_add((insn & 0xffff_f000) as i32 as i64 as u64),
+            },
+            OPCODE_JALR if funct3 == 0 => Insn::Jalr {
+                rd,
+                rs1,
+                offset: (insn as i32 >> 20) as i64,
+            },
+            _ => Insn::Other,
+        }
+    }
+
+    fn decode_compressed(insn: u16, pc: u64) -> Insn {
+        let insn = u32::from(insn);
+        if insn == 0x0001 {
+            return Insn::Nop;
+        }
+        // c.jr and c.jalr, quadrant 2 with funct3 = 0b100 and rs2 = 0.
+        let rs1 = (insn >> 7) & 0x1f;
+        if insn & 0b11 == 0b10 && insn >> 13 == 0b100 && (insn >> 2) & 0x1f == 0 && rs1 != 0 {
+            let rd = (insn >> 12) & 0x1;
+            return Insn::Jalr { rd, rs1, offset: 0 };
+        }
+        // c.j, quadrant 1 with funct3 = 0b101. (c.jal shares its encoding
+        // with c.addiw on RV64.)
+        if insn & 0b11 != 0b01 || insn >> 13 != 0b101 {
+            return Insn::Other;
+        }
+        let imm = ((insn >> 12) & 0x1) << 11
+            | ((insn >> 11) & 0x1) << 4
+            | ((insn >> 9) & 0x3) << 8
+            | ((insn >> 8) & 0x1) << 10
+            | ((insn >> 7) & 0x1) << 6
+            | ((insn >> 6) & 0x1) << 7
+            | ((insn >> 3) & 0x7) << 1
+            | ((insn >> 2) & 0x1) << 5;
+        Insn::Jal { rd: 0, target: pc.wrapping_add(sign_extend(imm, 12) as u64) }
+    }
+
+    fn sign_extend(value: u32, bits: u32) -> i64 {
+        let shift = 64 - bits;
+        ((value as i64) << shift) >> shift
+    }
+}
+
+#[cfg(feature = "disasm")]
+#[test]
+fn test_call_targets_x86_64() {
+    let code = [
+        0x50, // push %rax
+        0xe8, 0xfa, 0x0f, 0x00, 0x00, // call 0x402000
+        0x58, // pop %rax
+        0xc3, // ret
+        0xe9, 0xf3, 0x1f, 0x00, 0x00, // jmp 0x403000
+    ];
+    let targets = Arch::X86_64.call_targets(&code, 0x401000, &|_| None).unwrap();
+    assert_eq!(targets, [0x402000, 0x403000]);
+}
+
+#[cfg(feature = "disasm")]
+#[test]
+fn test_call_targets_x86_pic() {
+    let code = [
+        0x55, // push %ebp
+        0x89, 0xe5, // mov %esp, %ebp
+        0x53, // push %ebx
+        0x50, // push %eax
+        0xe8, 0xf6, 0x0f, 0x00, 0x00, // call __x86.get_pc_thunk.bx
+        0x81, 0xc3, 0xf6, 0x2f, 0x00, 0x00, // add $0x2ff6, %ebx
+        0xe8, 0xeb, 0x1f, 0x00, 0x00, // call 0x3000
+        0x83, 0xc4, 0x04, // add $4, %esp
+        0x5b, // pop %ebx
+        0x5d, // pop %ebp
+        0xc3, // ret
+        0xe8, 0x00, 0x00, 0x00, 0x00, // call 1f
+        0x5b, // 1: pop %ebx
+        0xe9, 0xda, 0x20, 0x00, 0x00, // jmp 0x3100
+    ];
+    let peek = |addr| match addr {
+        0x2000 => Some([0x8b, 0x1c, 0x24, 0xc3]),
+        _ => None,
+    };
+    let targets = Arch::X86.call_targets(&code, 0x1000, &peek).unwrap();
+    assert_eq!(targets, [0x3000, 0x3100]);
+}
+
+#[cfg(feature = "disasm")]
+#[test]
+fn test_element_targets_x86_64() {
+    let code = [
+        0x50, // push %rax
+        0xe8, 0xfa, 0x0f, 0x00, 0x00, // call 0x402000 (memcpy)
+        0xe8, 0xf5, 0x1f, 0x00, 0x00, // call 0x403000
+        0x58, // pop %rax
+        0xc3, // ret
+        0x0f, 0x1f, 0x00, // nopl (%rax)
+        0x00, 0x00, // padding
+        0xe9, 0xe9, 0x2f, 0x00, 0x00, // jmp 0x404000
+        0xcc, // int3
+    ];
+    let bodies = Arch::X86_64.bodies(&code, 0x401000, &|_| None).unwrap();
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0].len(), 5);
+    assert_eq!(bodies[1][0].address, 0x401012);
+    let elements = Arch::X86_64.elements(&code, 0x401000, &|_| None).unwrap();
+    assert_eq!(elements, [(0x403000, None, None), (0x404000, None, None)]);
+}
+
+#[cfg(feature = "disasm")]
+#[test]
+fn test_elements_with_metadata_x86_64() {
+    let code = [
+        0x48, 0x8d, 0x05, 0xf9, 0x0f, 0x00, 0x00, // lea 0x402000(%rip), %rax
+        0xc3, // ret
+        0x48, 0x8d, 0x05, 0xf1, 0x10, 0x00, 0x00, // lea 0x402100(%rip), %rax
+        0xc3, // ret
+        0x50, // push %rax
+        0xe8, 0xea, 0xff, 0xff, 0xff, // call 0x401000 (metadata)
+        0xe8, 0xed, 0xff, 0xff, 0xff, // call 0x401008 (key)
+        0x58, // pop %rax
+        0xe9, 0xdf, 0x1f, 0x00, 0x00, // jmp 0x403000
+    ];
+    let elements = Arch::X86_64.elements(&code, 0x401000, &|_| None).unwrap();
+    assert_eq!(elements, [(0x403000, Some(0x401000), Some(0x401008))]);
+}
+
+#[test]
+fn test_call_targets_riscv64() {
+    let code = [
+        0x41, 0x11, // c.addi sp, sp, -16
+        0x06, 0xe4, // c.sdsp ra, 8(sp)
+        0xef, 0x00, 0xc0, 0x0f, // jal ra, 0x10100
+        0xa2, 0x60, // c.ldsp ra, 8(sp)
+        0x41, 0x01, // c.addi sp, sp, 16
+        0x82, 0x80, // c.jr ra
+        0x6f, 0xf0, 0x3f, 0xfe, // j 0xfff0
+        0xfd, 0xb7, // c.j 0x10000
+        0x17, 0x03, 0x10, 0x00, // auipc t1, 0x100
+        0x67, 0x00, 0x83, 0x01, // jr 24(t1)
+        0x97, 0x00, 0x00, 0x00, // auipc ra, 0
+        0xe7, 0x80, 0x40, 0xff, // jalr -12(ra)
+    ];
+    let targets = Arch::RiscV64.call_targets(&code, 0x10000, &|_| None).unwrap();
+    assert_eq!(targets, [0x10100, 0xfff0, 0x10000, 0x11002c, 0x10010]);
+    let bodies = Arch::RiscV64.bodies(&code, 0x10000, &|_| None).unwrap();
+    assert_eq!(bodies.iter().map(Vec::len).collect::<Vec<_>>(), [6, 1, 1, 2, 2]);
+}
+
+#[test]
+fn test_duplicate_message() {
+    let declarations = [
+        Declaration { module_path: "app::handlers", file: "src/handlers.rs", line: 12 },
+        Declaration { module_path: "plugin", file: "plugin/src/lib.rs", line: 3 },
+    ];
+    assert_eq!(
+        duplicate_message("HANDLERS", &declarations),
+        "duplicate #[distributed_fn_slice] with name \"HANDLERS\", declared\n  \
+         in crate `app`, module `app::handlers`, at src/handlers.rs:12\n  \
+         in crate `plugin`, module `plugin`, at plugin/src/lib.rs:3",
+    );
+}
+
+#[cfg(feature = "symtab")]
+#[test]
+fn test_middle_impl_name() {
+    assert_eq!(
+        middle_impl_name("generics::elements::by_ret_val_1_generic_linkme_impl").as_deref(),
+        Some("generics::elements::by_ret_val_1_generic_linkme_impl::by_ret_val_1_middle_impl"),
+    );
+    assert_eq!(
+        middle_impl_name("generics::elements::by_ret_val_fn_trait_generic_linkme_impl::<fn(u32, u32) -> u32>").as_deref(),
+        Some("generics::elements::by_ret_val_fn_trait_generic_linkme_impl::by_ret_val_fn_trait_middle_impl::<fn(u32, u32) -> u32>"),
+    );
+    assert_eq!(middle_impl_name("generics::elements::helper::<u8>"), None);
 }
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use object::{Architecture, Object, ObjectSection, ObjectSymbol, SymbolKind};
+use once_cell::sync::OnceCell;
+
+use crate::distributed_fn_slice::Arch;
+
+// Owned view of the parts of an ELF file that symbol-based features need:
+// section headers and defined function symbols from both `.symtab` and
+// `.dynsym`.
+pub(crate) struct Image {
+    sections: Vec<Section>,
+    symbols: Vec<Symbol>,
+    by_name: OnceCell<HashMap<String, Vec<usize>>>,
+}
+
+pub(crate) struct Section {
+    pub(crate) name: String,
+    pub(crate) address: u64,
+    pub(crate) size: u64,
+}
+
+pub(crate) struct Symbol {
+    pub(crate) name: String,
+    pub(crate) address: u64,
+    pub(crate) size: u64,
+}
+
+impl Symbol {
+    /// Demangled name without the legacy mangling hash.
+    pub(crate) fn demangled(&self) -> String {
+        format!("{:#}", rustc_demangle::demangle(&self.name))
+    }
+}
+
+impl Image {
+    pub(crate) fn open(path: &Path) -> io::Result<Image> {
+        Image::parse(&fs::read(path)?)
+    }
+
+    pub(crate) fn parse(data: &[u8]) -> io::Result<Image> {
+        let file = object::File::parse(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let sections = file.sections()
+            .filter_map(|section| Some(Section {
+                name: section.name().ok()?.to_owned(),
+                address: section.address(),
+                size: section.size(),
+            }))
+            .collect();
+        let mut symbols: Vec<Symbol> = file.symbols()
+            .chain(file.dynamic_symbols())
+            .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+            .filter_map(|symbol| Some(Symbol {
+                name: symbol.name().ok()?.to_owned(),
+                address: symbol.address(),
+                size: symbol.size(),
+            }))
+            .collect();
+        symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+        symbols.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+        Ok(Image {
+            sections,
+            symbols,
+            by_name: OnceCell::new(),
+        })
+    }
+
+    /// The image of the executable or shared object that `addr` belongs to.
+    /// Images are parsed once and kept for the rest of the process.
+    pub(crate) fn containing(addr: usize) -> Option<&'static Image> {
+        static IMAGES: Mutex<Vec<(PathBuf, Option<&'static Image>)>> = Mutex::new(Vec::new());
+
+        let path = mapped_path(addr).or_else(|| env::current_exe().ok())?;
+        let mut images = IMAGES.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((_, image)) = images.iter().find(|(p, _)| *p == path) {
+            return *image;
+        }
+        let image = Image::open(&path).ok().map(|image| &*Box::leak(Box::new(image)));
+        images.push((path, image));
+        image
+    }
+
+    pub(crate) fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Function symbols starting inside `[start, end)`, in address order.
+    pub(crate) fn symbols_in(&self, start: u64, end: u64) -> &[Symbol] {
+        let lo = self.symbols.partition_point(|symbol| symbol.address < start);
+        let hi = self.symbols.partition_point(|symbol| symbol.address < end);
+        &self.symbols[lo..hi]
+    }
+
+    /// The function symbol whose body contains `addr`.
+    pub(crate) fn symbol_at(&self, addr: u64) -> Option<&Symbol> {
+        let i = self.symbols.partition_point(|symbol| symbol.address <= addr);
+        self.symbols[..i].iter().rev()
+            .take_while(|symbol| symbol.address == self.symbols[i - 1].address)
+            .find(|symbol| addr < symbol.address + symbol.size.max(1))
+    }
+
+    /// Function symbols whose demangled name, without hash, is `name`.
+    pub(crate) fn symbols_named(&self, name: &str) -> impl Iterator<Item = &Symbol> {
+        let by_name = self.by_name.get_or_init(|| {
+            let mut by_name = HashMap::<String, Vec<usize>>::new();
+            for (i, symbol) in self.symbols.iter().enumerate() {
+                by_name.entry(symbol.demangled()).or_default().push(i);
+            }
+            by_name
+        });
+        by_name.get(name).into_iter().flatten().map(move |&i| &self.symbols[i])
+    }
+}
+
+/// The architecture of `file`, if element bodies can be decoded for it.
+pub(crate) fn arch(file: &object::File) -> Option<Arch> {
+    match file.architecture() {
+        Architecture::I386 => Some(Arch::X86),
+        Architecture::X86_64 => Some(Arch::X86_64),
+        Architecture::Riscv64 => Some(Arch::RiscV64),
+        _ => None,
+    }
+}
+
+/// The first bytes at `addr` in `file`, for `Arch::call_targets`.
+pub(crate) fn peek(file: &object::File, addr: u64) -> Option<[u8; 4]> {
+    file.sections().find_map(|section| {
+        let bytes = section.data_range(addr, 4).ok()??;
+        bytes.try_into().ok()
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn mapped_path(addr: usize) -> Option<PathBuf> {
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines().find_map(|line| {
+        // start-end perms offset dev inode path
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        let path = fields.nth(4)?;
+        if (start..end).contains(&addr) && path.starts_with('/') {
+            Some(PathBuf::from(path))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mapped_path(_addr: usize) -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,202 @@
+use crate::__private::{ByMut, ByRef, ByValue, Trampoline};
+
+/// Element types of a [`DistributedFnSlice`](crate::DistributedFnSlice) that
+/// are not written out as a `fn` type in the declaration, such as a type alias
+/// or a `#[repr(transparent)]` newtype around a function pointer.
+///
+/// It is implemented for function pointers with up to 12 arguments, where
+/// each of the first three may also be a reference with an elided lifetime,
+/// as in `type Handler = fn(&Context) -> Response`, and can be derived for
+/// newtypes:
+///
+/// ```ignore
+/// #[derive(Clone, Copy, FnPointer)]
+/// #[repr(transparent)]
+/// pub struct Handler(fn(&Context) -> Response);
+/// ```
+///
+/// The slice holds [`Trampoline`](FnPointer::Trampoline) pointers and every
+/// element is checked against [`Signature`](FnPointer::Signature).
+///
+/// # Safety
+///
+/// `Trampoline` must be a function pointer with the calling convention of
+/// the generated trampolines and the same arguments and return type as
+/// `Signature`, which is what the derive emits.
+pub unsafe trait FnPointer: Copy + 'static {
+    /// The function pointer type the elements are stored as.
+    type Trampoline: Copy + 'static;
+    /// The function pointer type without a calling convention that the
+    /// elements have to match.
+    type Signature;
+}
+
+// The impls for `for<'a> fn(&'a T)` and `fn(T)` do not overlap, as `T` would
+// have to name the bound lifetime, which `coherence_leak_check` warns may
+// change. Unlike `Trampoline`, which only elements name, `FnPointer` cannot
+// take the shape of its arguments as a parameter: the declaration names its
+// `Trampoline` in the type of the static, where nothing infers the shape.
+#[allow(coherence_leak_check)]
+mod impls {
+    use super::FnPointer;
+
+    macro_rules! fn_pointer {
+        ($($lt:lifetime)* ; $($param:ident $(: ?$sized:ident)?),* ; $($arg:ty),*) => {
+            unsafe impl<R: 'static, $($param: $(?$sized +)? 'static),*> FnPointer for for<$($lt),*> fn($($arg),*) -> R {
+                type Trampoline = crate::__private::trampoline!({ for<$($lt),*> } { fn($($arg),*) -> R });
+                type Signature = Self;
+            }
+
+            unsafe impl<R: 'static, $($param: $(?$sized +)? 'static),*> FnPointer for for<$($lt),*> unsafe fn($($arg),*) -> R {
+                type Trampoline = crate::__private::trampoline!({ for<$($lt),*> unsafe } { fn($($arg),*) -> R });
+                type Signature = Self;
+            }
+        };
+    }
+
+    fn_pointer!(;;);
+    fn_pointer!(; A0; A0);
+    fn_pointer!('a; A0: ?Sized; &'a A0);
+    fn_pointer!('a; A0: ?Sized; &'a mut A0);
+    fn_pointer!(; A0, A1; A0, A1);
+    fn_pointer!('a; A0, A1: ?Sized; A0, &'a A1);
+    fn_pointer!('a; A0, A1: ?Sized; A0, &'a mut A1);
+    fn_pointer!('a; A0: ?Sized, A1; &'a A0, A1);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized; &'a A0, &'b A1);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized; &'a A0, &'b mut A1);
+    fn_pointer!('a; A0: ?Sized, A1; &'a mut A0, A1);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized; &'a mut A0, &'b A1);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized; &'a mut A0, &'b mut A1);
+    fn_pointer!(; A0, A1, A2; A0, A1, A2);
+    fn_pointer!('a; A0, A1, A2: ?Sized; A0, A1, &'a A2);
+    fn_pointer!('a; A0, A1, A2: ?Sized; A0, A1, &'a mut A2);
+    fn_pointer!('a; A0, A1: ?Sized, A2; A0, &'a A1, A2);
+    fn_pointer!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a A1, &'b A2);
+    fn_pointer!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a A1, &'b mut A2);
+    fn_pointer!('a; A0, A1: ?Sized, A2; A0, &'a mut A1, A2);
+    fn_pointer!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a mut A1, &'b A2);
+    fn_pointer!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a mut A1, &'b mut A2);
+    fn_pointer!('a; A0: ?Sized, A1, A2; &'a A0, A1, A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a A0, A1, &'b A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a A0, A1, &'b mut A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a A0, &'b A1, A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b A1, &'c A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b A1, &'c mut A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a A0, &'b mut A1, A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b mut A1, &'c A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b mut A1, &'c mut A2);
+    fn_pointer!('a; A0: ?Sized, A1, A2; &'a mut A0, A1, A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a mut A0, A1, &'b A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a mut A0, A1, &'b mut A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a mut A0, &'b A1, A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b A1, &'c A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b A1, &'c mut A2);
+    fn_pointer!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a mut A0, &'b mut A1, A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b mut A1, &'c A2);
+    fn_pointer!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b mut A1, &'c mut A2);
+    fn_pointer!(; A0, A1, A2, A3; A0, A1, A2, A3);
+    fn_pointer!(; A0, A1, A2, A3, A4; A0, A1, A2, A3, A4);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5; A0, A1, A2, A3, A4, A5);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6; A0, A1, A2, A3, A4, A5, A6);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6, A7; A0, A1, A2, A3, A4, A5, A6, A7);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6, A7, A8; A0, A1, A2, A3, A4, A5, A6, A7, A8);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+    fn_pointer!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+}
+
+/// Calls an element of a [`DistributedFnSlice`](crate::DistributedFnSlice)
+/// as it is stored: a function pointer with the calling convention of the
+/// trampolines, which unlike a plain function pointer does not implement
+/// `Fn`. The helpers for slices of hooks, commands and the like take their
+/// elements through it.
+///
+/// It is implemented for elements without arguments and for elements taking
+/// a single reference.
+pub trait Call<Args> {
+    type Output;
+
+    fn call(&self, args: Args) -> Self::Output;
+}
+
+impl<R> Call<()> for crate::__private::trampoline!({} { fn() -> R }) {
+    type Output = R;
+
+    fn call(&self, (): ()) -> R {
+        self()
+    }
+}
+
+impl<'c, A: ?Sized, R> Call<(&'c A,)> for crate::__private::trampoline!({ for<'a> } { fn(&'a A) -> R }) {
+    type Output = R;
+
+    fn call(&self, (arg,): (&'c A,)) -> R {
+        self(arg)
+    }
+}
+
+// Unlike `FnPointer`, `Trampoline` takes the shape of the arguments as a
+// parameter, so that references with an elided lifetime in the first three
+// arguments do not overlap with arguments passed by value. Other trampolines
+// with references are checked without their signature.
+macro_rules! trampoline {
+    ($($lt:lifetime)* ; $($param:ident $(: ?$sized:ident)?),* ; $($arg:ty),* ; $shape:ty) => {
+        impl<R, $($param $(: ?$sized)?),*> Trampoline<$shape> for crate::__private::trampoline!({ for<$($lt),*> } { fn($($arg),*) -> R }) {
+            type Signature = for<$($lt),*> fn($($arg),*) -> R;
+        }
+
+        impl<R, $($param $(: ?$sized)?),*> Trampoline<$shape> for crate::__private::trampoline!({ for<$($lt),*> unsafe } { fn($($arg),*) -> R }) {
+            type Signature = for<$($lt),*> unsafe fn($($arg),*) -> R;
+        }
+    };
+}
+
+trampoline!(;;; ());
+trampoline!(; A0; A0; (ByValue,));
+trampoline!('a; A0: ?Sized; &'a A0; (ByRef,));
+trampoline!('a; A0: ?Sized; &'a mut A0; (ByMut,));
+trampoline!(; A0, A1; A0, A1; (ByValue, ByValue));
+trampoline!('a; A0, A1: ?Sized; A0, &'a A1; (ByValue, ByRef));
+trampoline!('a; A0, A1: ?Sized; A0, &'a mut A1; (ByValue, ByMut));
+trampoline!('a; A0: ?Sized, A1; &'a A0, A1; (ByRef, ByValue));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized; &'a A0, &'b A1; (ByRef, ByRef));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized; &'a A0, &'b mut A1; (ByRef, ByMut));
+trampoline!('a; A0: ?Sized, A1; &'a mut A0, A1; (ByMut, ByValue));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized; &'a mut A0, &'b A1; (ByMut, ByRef));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized; &'a mut A0, &'b mut A1; (ByMut, ByMut));
+trampoline!(; A0, A1, A2; A0, A1, A2; (ByValue, ByValue, ByValue));
+trampoline!('a; A0, A1, A2: ?Sized; A0, A1, &'a A2; (ByValue, ByValue, ByRef));
+trampoline!('a; A0, A1, A2: ?Sized; A0, A1, &'a mut A2; (ByValue, ByValue, ByMut));
+trampoline!('a; A0, A1: ?Sized, A2; A0, &'a A1, A2; (ByValue, ByRef, ByValue));
+trampoline!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a A1, &'b A2; (ByValue, ByRef, ByRef));
+trampoline!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a A1, &'b mut A2; (ByValue, ByRef, ByMut));
+trampoline!('a; A0, A1: ?Sized, A2; A0, &'a mut A1, A2; (ByValue, ByMut, ByValue));
+trampoline!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a mut A1, &'b A2; (ByValue, ByMut, ByRef));
+trampoline!('a 'b; A0, A1: ?Sized, A2: ?Sized; A0, &'a mut A1, &'b mut A2; (ByValue, ByMut, ByMut));
+trampoline!('a; A0: ?Sized, A1, A2; &'a A0, A1, A2; (ByRef, ByValue, ByValue));
+trampoline!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a A0, A1, &'b A2; (ByRef, ByValue, ByRef));
+trampoline!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a A0, A1, &'b mut A2; (ByRef, ByValue, ByMut));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a A0, &'b A1, A2; (ByRef, ByRef, ByValue));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b A1, &'c A2; (ByRef, ByRef, ByRef));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b A1, &'c mut A2; (ByRef, ByRef, ByMut));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a A0, &'b mut A1, A2; (ByRef, ByMut, ByValue));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b mut A1, &'c A2; (ByRef, ByMut, ByRef));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a A0, &'b mut A1, &'c mut A2; (ByRef, ByMut, ByMut));
+trampoline!('a; A0: ?Sized, A1, A2; &'a mut A0, A1, A2; (ByMut, ByValue, ByValue));
+trampoline!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a mut A0, A1, &'b A2; (ByMut, ByValue, ByRef));
+trampoline!('a 'b; A0: ?Sized, A1, A2: ?Sized; &'a mut A0, A1, &'b mut A2; (ByMut, ByValue, ByMut));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a mut A0, &'b A1, A2; (ByMut, ByRef, ByValue));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b A1, &'c A2; (ByMut, ByRef, ByRef));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b A1, &'c mut A2; (ByMut, ByRef, ByMut));
+trampoline!('a 'b; A0: ?Sized, A1: ?Sized, A2; &'a mut A0, &'b mut A1, A2; (ByMut, ByMut, ByValue));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b mut A1, &'c A2; (ByMut, ByMut, ByRef));
+trampoline!('a 'b 'c; A0: ?Sized, A1: ?Sized, A2: ?Sized; &'a mut A0, &'b mut A1, &'c mut A2; (ByMut, ByMut, ByMut));
+trampoline!(; A0, A1, A2, A3; A0, A1, A2, A3; ByValue);
+trampoline!(; A0, A1, A2, A3, A4; A0, A1, A2, A3, A4; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5; A0, A1, A2, A3, A4, A5; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6; A0, A1, A2, A3, A4, A5, A6; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6, A7; A0, A1, A2, A3, A4, A5, A6, A7; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6, A7, A8; A0, A1, A2, A3, A4, A5, A6, A7, A8; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10; ByValue);
+trampoline!(; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11; A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11; ByValue);
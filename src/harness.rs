@@ -0,0 +1,308 @@
+use std::env;
+use std::io::{self, Write};
+use std::panic;
+use std::process;
+use std::time::{Duration, Instant};
+
+// Only this crate sees the trampoline ABI of the elements, which are never
+// called from C.
+#[crate::distributed_fn_slice]
+#[linkme(crate = crate)]
+#[allow(improper_ctypes_definitions)]
+pub static GENERIC_LINKME_TESTS: [fn() -> Case] = [..];
+
+#[crate::distributed_fn_slice]
+#[linkme(crate = crate)]
+#[allow(improper_ctypes_definitions)]
+pub static GENERIC_LINKME_BENCHES: [fn() -> Case] = [..];
+
+// Returned by the element that `#[generic_test]` or `#[generic_bench]` adds
+// for every type in its list.
+pub struct Case {
+    pub module_path: &'static str,
+    pub name: &'static str,
+    pub type_name: &'static str,
+    pub run: fn(),
+}
+
+impl Case {
+    // The name libtest would give a function at the same path, which leaves
+    // out the crate.
+    fn path(&self) -> String {
+        match self.module_path.split_once("::") {
+            Some((_, module)) => format!("{}::{}", module, self.name),
+            None => self.name.to_owned(),
+        }
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}::<{}>", self.path(), self.type_name)
+    }
+}
+
+// The subset of libtest's command line that selects tests. Options that only
+// affect how libtest runs them are accepted and ignored, others are rejected
+// like libtest does.
+#[derive(Default)]
+struct Options {
+    list: bool,
+    bench: bool,
+    exact: bool,
+    // Generic tests cannot be ignored, so `--ignored` selects none of them
+    // and `--include-ignored` changes nothing.
+    ignored: bool,
+    filters: Vec<String>,
+    skip: Vec<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            // `--option=value` is the same as `--option value`.
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || inline_value.clone().or_else(|| args.next());
+            match flag {
+                "--list" => options.list = true,
+                "--bench" => options.bench = true,
+                "--exact" => options.exact = true,
+                "--ignored" => options.ignored = true,
+                "--skip" => options.skip.extend(value()),
+                "--include-ignored" | "--test" | "--nocapture" | "--show-output" | "-q" | "--quiet"
+                | "--exclude-should-panic" | "--force-run-in-process" | "--report-time"
+                | "--ensure-time" | "--shuffle" => {}
+                "--test-threads" | "--color" | "--format" | "--logfile" | "--shuffle-seed" | "-Z" => {
+                    if value().is_none() {
+                        return Err(format!("Argument to option '{}' missing", flag.trim_start_matches('-')));
+                    }
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unrecognized option: '{}'", arg)),
+                _ => options.filters.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    fn matches(&self, name: &str, filter: &str) -> bool {
+        if self.exact {
+            name == filter
+        } else {
+            name.contains(filter)
+        }
+    }
+
+    fn selects(&self, name: &str) -> bool {
+        (self.filters.is_empty() || self.filters.iter().any(|filter| self.matches(name, filter)))
+            && !self.skip.iter().any(|filter| self.matches(name, filter))
+    }
+}
+
+// The selected cases in order of their full names, and how many were left
+// out.
+fn select(cases: Vec<Case>, options: &Options) -> (Vec<Case>, usize) {
+    let mut cases: Vec<(String, Case)> = cases.into_iter().map(|case| (case.full_name(), case)).collect();
+    // The same instantiation is registered once per crate that links it.
+    cases.sort_by(|a, b| a.0.cmp(&b.0));
+    cases.dedup_by(|a, b| a.0 == b.0);
+    let total = cases.len();
+    cases.retain(|(name, _)| !options.ignored && options.selects(name));
+    let filtered_out = total - cases.len();
+    (cases.into_iter().map(|(_, case)| case).collect(), filtered_out)
+}
+
+fn list(cases: &[Case], kind: &str) {
+    for case in cases {
+        println!("{}: {}", case.full_name(), kind);
+    }
+    let (tests, benches) = match kind {
+        "bench" => (0, cases.len()),
+        _ => (cases.len(), 0),
+    };
+    println!();
+    println!("{} {}, {} {}", tests, plural(tests, "test"), benches, plural(benches, "benchmark"));
+}
+
+/// Runs the generic tests of the program for a test target with
+/// `harness = false`, with output and exit status following libtest.
+pub fn test_main() {
+    let options = parse_args();
+    let tests = GENERIC_LINKME_TESTS.iter().map(|test| test()).collect();
+    let (tests, filtered_out) = select(tests, &options);
+    if options.list {
+        list(&tests, "test");
+    } else {
+        run_once(&tests, filtered_out);
+    }
+}
+
+/// Runs the generic benchmarks of the program for a bench target with
+/// `harness = false`. Like libtest, it only measures them when given
+/// `--bench`, as `cargo bench` does, and otherwise runs each once as a test.
+pub fn bench_main() {
+    let options = parse_args();
+    let benches = GENERIC_LINKME_BENCHES.iter().map(|bench| bench()).collect();
+    let (benches, filtered_out) = select(benches, &options);
+    if options.list {
+        list(&benches, "bench");
+    } else if options.bench {
+        measure_all(&benches, filtered_out);
+    } else {
+        run_once(&benches, filtered_out);
+    }
+}
+
+fn parse_args() -> Options {
+    match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(101);
+        }
+    }
+}
+
+fn run_once(cases: &[Case], filtered_out: usize) {
+    println!();
+    println!("running {} {}", cases.len(), plural(cases.len(), "test"));
+    let start = Instant::now();
+    let mut failures = Vec::new();
+    for case in cases {
+        let name = case.full_name();
+        print!("test {} ... ", name);
+        let _ = io::stdout().flush();
+        if panic::catch_unwind(case.run).is_ok() {
+            println!("ok");
+        } else {
+            println!("FAILED");
+            failures.push(name);
+        }
+    }
+
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        for name in &failures {
+            println!("    {}", name);
+        }
+    }
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; 0 ignored; 0 measured; {} filtered out; finished in {:.2}s",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        cases.len() - failures.len(),
+        failures.len(),
+        filtered_out,
+        start.elapsed().as_secs_f64(),
+    );
+    println!();
+    if !failures.is_empty() {
+        process::exit(101);
+    }
+}
+
+const WARM_UP: Duration = Duration::from_millis(100);
+const SAMPLES: usize = 30;
+const SAMPLE_TIME: Duration = Duration::from_millis(5);
+
+// Nanoseconds per iteration over the samples.
+struct Summary {
+    median: f64,
+    mean: f64,
+    stddev: f64,
+}
+
+// Runs the benchmark for the warm-up time, which also estimates how many
+// iterations fill a sample, then times each sample.
+fn measure(run: fn()) -> Summary {
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    while iterations == 0 || start.elapsed() < WARM_UP {
+        run();
+        iterations += 1;
+    }
+    let per_iteration = start.elapsed().as_nanos() as f64 / iterations as f64;
+    let batch = (SAMPLE_TIME.as_nanos() as f64 / per_iteration.max(1.0)).ceil().max(1.0) as u64;
+
+    let mut samples: Vec<f64> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..batch {
+                run();
+            }
+            start.elapsed().as_nanos() as f64 / batch as f64
+        })
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    let median = match SAMPLES % 2 {
+        0 => (samples[SAMPLES / 2 - 1] + samples[SAMPLES / 2]) / 2.0,
+        _ => samples[SAMPLES / 2],
+    };
+    let mean = samples.iter().sum::<f64>() / SAMPLES as f64;
+    let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (SAMPLES - 1) as f64;
+    Summary {
+        median,
+        mean,
+        stddev: variance.sqrt(),
+    }
+}
+
+// One row per benchmark and type argument, keyed by the `type_name` of the
+// type argument.
+fn measure_all(benches: &[Case], filtered_out: usize) {
+    println!();
+    println!("running {} {}", benches.len(), plural(benches.len(), "benchmark"));
+    let start = Instant::now();
+    let rows: Vec<(String, &str, Summary)> = benches
+        .iter()
+        .map(|bench| (bench.path(), bench.type_name, measure(bench.run)))
+        .collect();
+
+    let bench_width = rows.iter().map(|row| row.0.len()).chain(Some("bench".len())).max().unwrap();
+    let type_width = rows.iter().map(|row| row.1.len()).chain(Some("type".len())).max().unwrap();
+    println!();
+    println!(
+        "{:<bench_width$}  {:<type_width$}  {:>10}  {:>10}  {:>10}",
+        "bench", "type", "median", "mean", "stddev",
+    );
+    for (bench, type_name, summary) in &rows {
+        println!(
+            "{:<bench_width$}  {:<type_width$}  {:>10}  {:>10}  {:>10}",
+            bench,
+            type_name,
+            format_nanos(summary.median),
+            format_nanos(summary.mean),
+            format_nanos(summary.stddev),
+        );
+    }
+    println!();
+    println!(
+        "test result: ok. 0 passed; 0 failed; 0 ignored; {} measured; {} filtered out; finished in {:.2}s",
+        rows.len(),
+        filtered_out,
+        start.elapsed().as_secs_f64(),
+    );
+    println!();
+}
+
+fn format_nanos(nanos: f64) -> String {
+    if nanos < 1e3 {
+        format!("{:.1} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.1} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.1} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        word.to_owned()
+    } else {
+        format!("{}s", word)
+    }
+}
@@ -0,0 +1,161 @@
+//! Running the handlers that `#[hook]` adds to a slice.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::distributed_fn_slice::{DistributedFnSlice, Entry};
+use crate::fn_pointer::Call;
+
+/// What a handler returns: nothing, or a `Result` whose error is collected.
+pub trait HookOutput {
+    type Error;
+
+    fn into_result(self) -> Result<(), Self::Error>;
+
+    /// Returned by a handler in place of its output after it panicked, which
+    /// [`run`] reports instead.
+    fn panicked() -> Self;
+}
+
+impl HookOutput for () {
+    type Error = Infallible;
+
+    fn into_result(self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn panicked() -> Self {}
+}
+
+impl<E> HookOutput for Result<(), E> {
+    type Error = E;
+
+    fn into_result(self) -> Result<(), E> {
+        self
+    }
+
+    fn panicked() -> Self {
+        Ok(())
+    }
+}
+
+thread_local! {
+    static PANICKED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Wraps the body of every `#[hook]`. Panics cannot unwind out of the
+// elements, whose trampolines have a calling convention without unwinding,
+// so they are caught inside and the message left for `run`.
+#[doc(hidden)]
+pub fn private_catch<O: HookOutput>(hook: impl FnOnce() -> O) -> O {
+    match panic::catch_unwind(AssertUnwindSafe(hook)) {
+        Ok(output) => output,
+        Err(payload) => {
+            let message = panic_message(&*payload);
+            PANICKED.with(|panicked| *panicked.borrow_mut() = Some(message));
+            O::panicked()
+        }
+    }
+}
+
+/// How a handler failed.
+#[derive(Debug)]
+pub enum HookError<E> {
+    /// The handler panicked, with this message if it was a string.
+    Panicked(String),
+    /// The handler returned this error.
+    Failed(E),
+}
+
+impl<E: fmt::Display> fmt::Display for HookError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookError::Panicked(message) => write!(f, "panicked: {}", message),
+            HookError::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// A handler that failed, with its element in the slice.
+#[derive(Debug)]
+pub struct Failure<E> {
+    /// The element of the handler in the slice.
+    pub entry: Entry,
+    /// How the handler failed.
+    pub error: HookError<E>,
+}
+
+/// The outcome of [`run`].
+#[derive(Debug)]
+pub struct Report<E> {
+    /// The elements of the handlers in the order they ran.
+    pub ran: Vec<Entry>,
+    /// The handlers that returned an error or panicked, in the order they
+    /// ran.
+    pub failures: Vec<Failure<E>>,
+}
+
+impl<E> Report<E> {
+    /// Whether every handler succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Calls every handler in `hooks` with `ctx`: those with a priority first,
+/// lowest first, then the others, each group ordered by name, then by symbol
+/// with the `symtab` feature, then by the [type arguments](Entry::type_args)
+/// that tell the instantiations of one generic handler apart, and then by
+/// their order in the slice. A handler that panics or returns an error does
+/// not stop the others; its failure is collected in the report. Only handlers
+/// added with `#[hook]` can recover from a panic, which aborts the process in
+/// any other element.
+pub fn run<C, F, O>(hooks: &DistributedFnSlice<[F]>, ctx: &C) -> Report<O::Error>
+where
+    C: ?Sized,
+    F: for<'c> Call<(&'c C,), Output = O> + 'static,
+    O: HookOutput,
+{
+    let mut entries: Vec<Entry> = (0..hooks.len()).filter_map(|index| hooks.describe(index)).collect();
+    entries.sort_by(|a, b| {
+        let key = |entry: &Entry| (entry.pos().is_none(), entry.pos(), entry.name());
+        key(a).cmp(&key(b))
+            .then_with(|| a.symbol().cmp(&b.symbol()))
+            .then_with(|| a.type_args().cmp(&b.type_args()))
+            .then_with(|| a.index().cmp(&b.index()))
+    });
+
+    let mut failures = Vec::new();
+    for entry in &entries {
+        PANICKED.with(|panicked| panicked.borrow_mut().take());
+        let output = hooks[entry.index()].call((ctx,));
+        let error = match PANICKED.with(|panicked| panicked.borrow_mut().take()) {
+            Some(message) => HookError::Panicked(message),
+            None => match output.into_result() {
+                Ok(()) => continue,
+                Err(error) => HookError::Failed(error),
+            },
+        };
+        failures.push(Failure {
+            entry: entry.clone(),
+            error,
+        });
+    }
+    Report {
+        ran: entries,
+        failures,
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
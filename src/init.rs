@@ -0,0 +1,155 @@
+//! Running the init functions of a slice in the order that their `after` and
+//! `before` arguments give.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::distributed_fn_slice::{DistributedFnSlice, Entry};
+use crate::fn_pointer::Call;
+
+/// Why the init functions of a slice cannot be ordered. None of them ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InitError {
+    /// The element's `after` or `before` names `name`, which no element of
+    /// the slice has.
+    Missing { entry: Box<Entry>, name: &'static str },
+    /// Elements that have to run after each other: each after the previous
+    /// one, and the first after the last.
+    Cycle(Vec<Entry>),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitError::Missing { entry, name } => {
+                write!(f, "init {} is ordered against {:?}, which no element of the slice is named", label(entry), name)
+            }
+            InitError::Cycle(entries) => {
+                write!(f, "cycle between init functions: ")?;
+                for entry in entries {
+                    write!(f, "{} -> ", label(entry))?;
+                }
+                write!(f, "{}", label(&entries[0]))
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+fn label(entry: &Entry) -> String {
+    match entry.name() {
+        Some(name) => format!("{:?}", name),
+        None => format!("[{}]", entry.index()),
+    }
+}
+
+type Outcome = Result<Vec<Entry>, InitError>;
+
+// One cell per slice, leaked so that it can be initialized without holding
+// the lock, which an init function running another slice would need.
+static OUTCOMES: Lazy<Mutex<HashMap<usize, &'static OnceCell<Outcome>>>> = Lazy::new(Default::default);
+
+/// Calls every init function in `inits` once per process, each after the
+/// elements named in its `after` and before those named in its `before`.
+/// An element with a name stands for all elements with that name, such as
+/// the instantiations of a generic function. Elements the relations leave
+/// unordered run by `pos`, lowest first and those without one last, then by
+/// name and then by their order in the slice.
+///
+/// Later calls, also from other threads, wait for the first one and return
+/// its outcome: the elements in the order they ran, or why they could not be
+/// ordered, in which case none ran. An init function must not run the slice
+/// it belongs to, which deadlocks.
+pub fn run<F: Call<(), Output = ()> + 'static>(inits: &'static DistributedFnSlice<[F]>) -> Result<&'static [Entry], InitError> {
+    let key = inits as *const DistributedFnSlice<[F]> as usize;
+    let cell = *OUTCOMES.lock().unwrap().entry(key).or_insert_with(|| Box::leak(Box::default()));
+    let outcome = cell.get_or_init(|| {
+        let entries = (0..inits.len()).filter_map(|index| inits.describe(index)).collect();
+        let order = order(entries)?;
+        for entry in &order {
+            inits[entry.index()].call(());
+        }
+        Ok(order)
+    });
+    match outcome {
+        Ok(order) => Ok(order),
+        Err(err) => Err(err.clone()),
+    }
+}
+
+// Kahn's algorithm, taking the first ready element by the tie-breaking key.
+fn order(entries: Vec<Entry>) -> Outcome {
+    let mut by_name = HashMap::<&str, Vec<usize>>::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(name) = entry.name() {
+            by_name.entry(name).or_default().push(i);
+        }
+    }
+    let named = |entry: &Entry, name: &'static str| {
+        by_name.get(name).ok_or_else(|| InitError::Missing {
+            entry: Box::new(entry.clone()),
+            name,
+        })
+    };
+
+    let mut predecessors = vec![Vec::new(); entries.len()];
+    let mut successors = vec![Vec::new(); entries.len()];
+    for (i, entry) in entries.iter().enumerate() {
+        for &name in entry.after() {
+            for &before in named(entry, name)? {
+                predecessors[i].push(before);
+                successors[before].push(i);
+            }
+        }
+        for &name in entry.before() {
+            for &after in named(entry, name)? {
+                predecessors[after].push(i);
+                successors[i].push(after);
+            }
+        }
+    }
+
+    let key = |i: usize| {
+        let entry = &entries[i];
+        (entry.pos().is_none(), entry.pos(), entry.name(), i)
+    };
+    let mut waiting: Vec<usize> = predecessors.iter().map(Vec::len).collect();
+    let mut ready: BTreeSet<_> = (0..entries.len()).filter(|&i| waiting[i] == 0).map(key).collect();
+    let mut order = Vec::with_capacity(entries.len());
+    while let Some(first) = ready.iter().next().copied() {
+        ready.remove(&first);
+        let i = first.3;
+        order.push(i);
+        for &next in &successors[i] {
+            waiting[next] -= 1;
+            if waiting[next] == 0 {
+                ready.insert(key(next));
+            }
+        }
+    }
+
+    if order.len() < entries.len() {
+        return Err(InitError::Cycle(cycle(&predecessors, &waiting).into_iter().map(|i| entries[i].clone()).collect()));
+    }
+    Ok(order.into_iter().map(|i| entries[i].clone()).collect())
+}
+
+// Walks back from the first element that never became ready through
+// predecessors that did not either, which has to come back to an element it
+// passed.
+fn cycle(predecessors: &[Vec<usize>], waiting: &[usize]) -> Vec<usize> {
+    let mut path = Vec::new();
+    let mut i = waiting.iter().position(|&n| n > 0).unwrap();
+    while !path.contains(&i) {
+        path.push(i);
+        i = *predecessors[i].iter().find(|&&before| waiting[before] > 0).unwrap();
+    }
+    let start = path.iter().position(|&j| j == i).unwrap();
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
+    cycle
+}
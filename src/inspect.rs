@@ -0,0 +1,177 @@
+//! Offline description of the distributed slices of an ELF executable or
+//! shared object, for finding out why an element is missing from a release
+//! artifact without rebuilding it.
+//!
+//! The `generic-linkme-inspect` binary prints [`inspect`] for a path.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use object::{Object, ObjectSection};
+
+use crate::distributed_fn_slice::Arch;
+use crate::elf::{self, Image};
+use crate::table::{CAPACITY, RESOLVED};
+
+#[derive(Default)]
+struct Slice<'a> {
+    section: Option<object::Section<'a, 'a>>,
+    dupcheck: Option<object::Section<'a, 'a>>,
+    metadata: Option<object::Section<'a, 'a>>,
+    table: Option<object::Section<'a, 'a>>,
+}
+
+/// Describes every `generic_linkme_*` section of the file at `path`: the
+/// element bodies, the targets they resolve to, the number of declarations
+/// recorded in `generic_linkm2_*`, the post-link table and the sort keys of
+/// the elements with a `pos` in `generic_linkm3_*`.
+pub fn inspect(path: &Path) -> io::Result<String> {
+    let data = fs::read(path)?;
+    let file = object::File::parse(&*data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let image = Image::parse(&data)?;
+    let arch = elf::arch(&file);
+    let word = if arch == Some(Arch::X86) { 4 } else { 8 };
+
+    let mut slices = BTreeMap::<String, Slice>::new();
+    for section in file.sections() {
+        let Ok(name) = section.name().map(str::to_owned) else { continue };
+        let name = name.strip_prefix("set_").unwrap_or(&name);
+        if let Some(slice) = name.strip_prefix("generic_linkme_tbl_") {
+            slices.entry(slice.to_owned()).or_default().table = Some(section);
+        } else if let Some(slice) = name.strip_prefix("generic_linkm2_") {
+            slices.entry(slice.to_owned()).or_default().dupcheck = Some(section);
+        } else if let Some(slice) = name.strip_prefix("generic_linkm3_") {
+            slices.entry(slice.to_owned()).or_default().metadata = Some(section);
+        } else if let Some(slice) = name.strip_prefix("generic_linkme_") {
+            slices.entry(slice.to_owned()).or_default().section = Some(section);
+        }
+    }
+
+    let mut res = String::new();
+    if slices.is_empty() {
+        writeln!(&mut res, "no distributed_fn_slice sections").unwrap();
+    }
+    for (name, slice) in &slices {
+        writeln!(&mut res, "{}", name).unwrap();
+        match &slice.dupcheck {
+            Some(section) => {
+                // One `__private::Declaration`, five words, per declaration.
+                let count = section.size() / (5 * word);
+                let status = if count == 1 { "ok" } else { "duplicate" };
+                writeln!(&mut res, "  declarations: {} ({})", count, status).unwrap();
+            }
+            None => writeln!(&mut res, "  declarations: none found").unwrap(),
+        }
+        match slice.table.as_ref().map(|section| section.data()) {
+            Some(Ok(bytes)) if bytes.len() >= 8 => {
+                let magic = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+                let len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+                if magic == RESOLVED && len as usize <= CAPACITY {
+                    writeln!(&mut res, "  table: {} offsets, written by generic-linkme-postlink", len).unwrap();
+                } else {
+                    writeln!(&mut res, "  table: not resolved").unwrap();
+                }
+            }
+            _ => writeln!(&mut res, "  table: none").unwrap(),
+        }
+        match &slice.section {
+            Some(section) => {
+                let section_name = section.name().unwrap_or_default();
+                writeln!(&mut res, "  section {} at {:#x}, {} bytes", section_name, section.address(), section.size()).unwrap();
+                let keys = slice.metadata.as_ref().map_or_else(BTreeMap::new, |metadata| sort_keys(&file, word, metadata));
+                describe_section(&mut res, &file, &image, arch, section, &keys);
+            }
+            None => writeln!(&mut res, "  no elements section").unwrap(),
+        }
+    }
+    Ok(res)
+}
+
+// The positions in the `__private::Metadata` records of a slice, eight words
+// each, by the metadata function that the bodies of the element call.
+fn sort_keys(file: &object::File, word: u64, section: &object::Section) -> BTreeMap<u64, u64> {
+    let Ok(data) = section.data() else { return BTreeMap::new() };
+    // In a position-independent executable the function pointer is only
+    // written by the dynamic loader, from the addend of its relocation.
+    let addends: BTreeMap<u64, u64> = file.dynamic_relocations()
+        .into_iter()
+        .flatten()
+        .filter(|(_, relocation)| !relocation.has_implicit_addend())
+        .map(|(offset, relocation)| (offset, relocation.addend() as u64))
+        .collect();
+    let read = |offset: u64| {
+        let bytes = data.get(offset as usize..(offset + word) as usize)?;
+        Some(match *bytes {
+            [a, b, c, d] => u32::from_le_bytes([a, b, c, d]) as u64,
+            _ => u64::from_le_bytes(bytes.try_into().ok()?),
+        })
+    };
+    let mut keys = BTreeMap::new();
+    let mut offset = 0;
+    while let (Some(function), Some(pos)) = (read(offset), read(offset + word)) {
+        let function = addends.get(&(section.address() + offset)).copied().unwrap_or(function);
+        let none = if word == 4 { u32::MAX as u64 } else { u64::MAX };
+        if pos != none {
+            keys.insert(function, pos);
+        }
+        offset += 8 * word;
+    }
+    keys
+}
+
+fn describe_section(
+    res: &mut String,
+    file: &object::File,
+    image: &Image,
+    arch: Option<Arch>,
+    section: &object::Section,
+    keys: &BTreeMap<u64, u64>,
+) {
+    let Ok(code) = section.data() else {
+        writeln!(res, "    no contents in the file").unwrap();
+        return;
+    };
+    let start = section.address();
+    let peek = |addr| elf::peek(file, addr);
+    let targets = |addr: u64, size: u64| {
+        let offset = (addr - start) as usize;
+        let body = code.get(offset..offset + size as usize)?;
+        arch?.call_targets(body, addr, &peek)
+    };
+    let bodies = image.symbols_in(start, start + section.size());
+    if bodies.is_empty() {
+        writeln!(res, "    no symbols for the element bodies").unwrap();
+        describe_targets(res, image, keys, targets(start, code.len() as u64));
+    }
+    for body in bodies {
+        writeln!(res, "    {:#x} {} ({} bytes)", body.address, body.demangled(), body.size).unwrap();
+        describe_targets(res, image, keys, targets(body.address, body.size));
+    }
+}
+
+// The metadata functions are recognized by the records that point to them,
+// so their sort keys are found without symbols too.
+fn describe_targets(res: &mut String, image: &Image, keys: &BTreeMap<u64, u64>, targets: Option<Vec<u64>>) {
+    let Some(targets) = targets else {
+        writeln!(res, "      targets unknown, no disassembler for this architecture").unwrap();
+        return;
+    };
+    for target in targets {
+        match image.symbol_at(target) {
+            Some(symbol) if symbol.address == target => {
+                write!(res, "      -> {:#x} {}", target, symbol.demangled()).unwrap();
+            }
+            Some(symbol) => {
+                write!(res, "      -> {:#x} {}+{:#x}", target, symbol.demangled(), target - symbol.address).unwrap();
+            }
+            None => write!(res, "      -> {:#x}", target).unwrap(),
+        }
+        match keys.get(&target) {
+            Some(key) => writeln!(res, ", sort key {:04}", key).unwrap(),
+            None => writeln!(res).unwrap(),
+        }
+    }
+}
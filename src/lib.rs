@@ -1,5 +1,25 @@
+#[cfg(feature = "c_api")]
+pub mod c_api;
+pub mod commands;
+pub mod di;
+pub mod dispatch;
 mod distributed_fn_slice;
+#[cfg(feature = "symtab")]
+mod elf;
+mod fn_pointer;
+mod harness;
+pub mod hooks;
+pub mod init;
+#[cfg(feature = "symtab")]
+pub mod inspect;
 mod link;
+#[cfg(feature = "symtab")]
+pub mod postlink;
+mod report;
+#[cfg(feature = "serde")]
+pub mod serde_registry;
+mod table;
+mod types;
 
 // Not public API.
 #[doc(hidden)]
@@ -8,6 +28,10 @@ pub mod __private;
 
 pub use generic_linkme_impl::*;
 
-pub use crate::distributed_fn_slice::DistributedFnSlice;
+pub use crate::distributed_fn_slice::{DistributedFnSlice, Entry, Extraction};
+pub use crate::fn_pointer::{Call, FnPointer};
+pub use crate::harness::{bench_main, test_main};
+pub use crate::report::{BodyReport, Candidate, DisassemblyReport, Instruction, Reason};
+pub use crate::types::{register_type, TypeInfo, GENERIC_LINKME_TYPES as TYPES};
 
 pub use crate::link::link;
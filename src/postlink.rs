@@ -0,0 +1,137 @@
+//! Post-link step that resolves every distributed slice of an ELF executable
+//! or shared object ahead of time.
+//!
+//! [`patch`] disassembles each `generic_linkme_<NAME>` section of the file
+//! once and writes the offsets of the elements and their metadata and keys
+//! into the slice's reserved `generic_linkme_tbl_<NAME>` section, which the
+//! program then reads instead of disassembling its own code at startup. The program has to be built with
+//! the `postlink` feature, which reserves the tables, and can then be built
+//! without the `disasm` feature.
+//!
+//! The `generic-linkme-postlink` binary wraps this for use as a cargo runner,
+//! e.g. in `.cargo/config.toml`:
+//!
+//! ```toml
+//! [target.'cfg(target_os = "linux")']
+//! runner = ["generic-linkme-postlink", "--run"]
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use object::{Object, ObjectSection};
+
+use crate::elf;
+use crate::table::{CAPACITY, NO_METADATA, RESOLVED};
+
+/// The table of one slice as written by [`patch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolved {
+    /// Name of the slice's static.
+    pub name: String,
+    /// Offsets of the elements relative to the start of the slice's section.
+    pub offsets: Vec<i32>,
+    /// Offsets of the elements' metadata functions, for elements with a
+    /// name or position.
+    pub metadata: Vec<Option<i32>>,
+    /// Offsets of the key functions of the instantiations of generic
+    /// elements with a name or position.
+    pub keys: Vec<Option<i32>>,
+}
+
+/// Resolves the slices of the file at `path` and rewrites it in place.
+/// Running it again on a patched file is harmless.
+pub fn patch(path: &Path) -> io::Result<Vec<Resolved>> {
+    let mut data = fs::read(path)?;
+    let resolved = patch_bytes(&mut data)?;
+    if !resolved.is_empty() {
+        fs::write(path, &data)?;
+    }
+    Ok(resolved)
+}
+
+/// Same as [`patch`], on the contents of a file.
+pub fn patch_bytes(data: &mut [u8]) -> io::Result<Vec<Resolved>> {
+    let file = object::File::parse(&*data).map_err(invalid)?;
+    let arch = elf::arch(&file)
+        .ok_or_else(|| invalid(format!("unsupported architecture {:?}", file.architecture())))?;
+    let peek = |addr| elf::peek(&file, addr);
+
+    let mut resolved = Vec::new();
+    let mut writes = Vec::new();
+    for table in file.sections() {
+        let Ok(table_name) = table.name() else { continue };
+        let (prefix, name) = match table_name.strip_prefix("set_") {
+            Some(rest) => ("set_", rest),
+            None => ("", table_name),
+        };
+        let Some(name) = name.strip_prefix("generic_linkme_tbl_") else { continue };
+
+        let (start, code) = match file.section_by_name(&format!("{}generic_linkme_{}", prefix, name)) {
+            Some(section) => (section.address(), section.data().map_err(invalid)?),
+            None => (0, &[][..]),
+        };
+        let elements = arch.elements(code, start, &peek)
+            .ok_or_else(|| invalid(format!("no disassembler for {:?}, enable the `disasm` feature", arch)))?;
+        if elements.len() > CAPACITY {
+            return Err(invalid(format!(
+                "distributed_fn_slice {} has {} elements, at most {} are supported",
+                name, elements.len(), CAPACITY,
+            )));
+        }
+        let offset = |address: u64| {
+            i32::try_from(address.wrapping_sub(start) as i64)
+                .map_err(|_| invalid(format!("distributed_fn_slice {} has an element out of range", name)))
+        };
+        let offsets = elements.iter()
+            .map(|&(target, _, _)| offset(target))
+            .collect::<io::Result<Vec<i32>>>()?;
+        let metadata = elements.iter()
+            .map(|&(_, metadata, _)| metadata.map(offset).transpose())
+            .collect::<io::Result<Vec<Option<i32>>>>()?;
+        let keys = elements.iter()
+            .map(|&(_, _, key)| key.map(offset).transpose())
+            .collect::<io::Result<Vec<Option<i32>>>>()?;
+
+        let (file_offset, size) = table.file_range()
+            .ok_or_else(|| invalid(format!("{} has no contents in the file", table_name)))?;
+        let bytes = encode(&offsets, &metadata, &keys);
+        if (size as usize) < bytes.len() {
+            return Err(invalid(format!("{} is too small", table_name)));
+        }
+        writes.push((file_offset as usize, bytes));
+        resolved.push(Resolved {
+            name: name.to_owned(),
+            offsets,
+            metadata,
+            keys,
+        });
+    }
+
+    for (offset, bytes) in writes {
+        data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    }
+    Ok(resolved)
+}
+
+// Little-endian image of a resolved `Table`. All supported targets are
+// little-endian.
+fn encode(offsets: &[i32], metadata: &[Option<i32>], keys: &[Option<i32>]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 * (2 + 3 * CAPACITY));
+    bytes.extend(RESOLVED.to_le_bytes());
+    bytes.extend((offsets.len() as u32).to_le_bytes());
+    for i in 0..CAPACITY {
+        bytes.extend(offsets.get(i).copied().unwrap_or(0).to_le_bytes());
+    }
+    for i in 0..CAPACITY {
+        bytes.extend(metadata.get(i).copied().flatten().unwrap_or(NO_METADATA).to_le_bytes());
+    }
+    for i in 0..CAPACITY {
+        bytes.extend(keys.get(i).copied().flatten().unwrap_or(NO_METADATA).to_le_bytes());
+    }
+    bytes
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -1,7 +1,103 @@
+pub use core::any::type_name;
 pub use core::assert;
+pub use core::hint::black_box;
+pub use core::file;
+pub use core::line;
 pub use core::mem;
+pub use core::marker::PhantomData;
+pub use core::module_path;
 pub use core::primitive::usize;
 pub use core::primitive::u8;
+pub use std::sync::Arc;
+
+pub use crate::harness::{Case, GENERIC_LINKME_BENCHES, GENERIC_LINKME_TESTS};
+pub use crate::table::Table;
+pub use crate::DistributedFnSlice;
+
+#[cfg(feature = "c_api")]
+pub use crate::c_api::{Export, GENERIC_LINKME_REGISTRIES};
+#[cfg(feature = "serde")]
+pub use erased_serde;
+#[cfg(feature = "serde")]
+pub use serde;
+
+// Written to `generic_linkm2_<NAME>` by every declaration of a slice, so that
+// duplicates can be reported with their locations.
+#[repr(C)]
+pub struct Declaration {
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+}
+
+// Placed in `generic_linkm3_<NAME>` by every element with a `name`, `pos`,
+// `after` or `before`, next to the function that its bodies call, which
+// returns it. The runtime only compares the addresses of the functions the
+// bodies call with `function` and never calls them. The layout is fixed so
+// that `generic-linkme-inspect` can read the records from a binary.
+#[repr(C)]
+pub struct Metadata {
+    pub function: extern "C" fn() -> &'static Metadata,
+    // `usize::MAX` for elements without a position, which the attribute
+    // rejects as one.
+    pub pos: usize,
+    pub name: Option<&'static str>,
+    pub after: &'static [&'static str],
+    pub before: &'static [&'static str],
+}
+
+impl Metadata {
+    pub fn pos(&self) -> Option<usize> {
+        if self.pos == usize::MAX {
+            None
+        } else {
+            Some(self.pos)
+        }
+    }
+}
+
+// Hides the value a metadata function returns from the optimizer, which would
+// otherwise propagate it to the callers and remove it from the function. It is
+// always inlined, so that the function makes no calls, even without
+// optimizations.
+#[inline(always)]
+pub fn opaque(metadata: &'static Metadata) -> &'static Metadata {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64"))]
+    unsafe {
+        let mut ptr = metadata as *const Metadata;
+        core::arch::asm!("/* {} */", inout(reg) ptr, options(nostack, preserves_flags));
+        // Not `&*ptr`, which debug builds check with a call to a panic.
+        #[allow(clippy::transmute_ptr_to_ref)]
+        core::mem::transmute::<*const Metadata, &'static Metadata>(ptr)
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64")))]
+    unsafe {
+        core::ptr::read_volatile(&metadata)
+    }
+}
+
+// Returned by the function that the bodies of a generic element with
+// metadata call after the metadata function. That function is generic, one
+// per instantiation, and is called by the runtime for the type arguments it
+// was instantiated with. It makes no calls itself, as its bodies would be
+// taken for elements otherwise, so the names are put together by the function
+// it returns, outside of the section.
+pub type Key = fn() -> String;
+
+// As `opaque`, for the key of an instantiation.
+#[inline(always)]
+pub fn opaque_key(key: Key) -> Key {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64"))]
+    unsafe {
+        let mut ptr = key as *const ();
+        core::arch::asm!("/* {} */", inout(reg) ptr, options(nostack, preserves_flags));
+        core::mem::transmute::<*const (), Key>(ptr)
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64")))]
+    unsafe {
+        core::ptr::read_volatile(&key)
+    }
+}
 
 pub trait Slice {
     type Element;
@@ -13,6 +109,136 @@ impl<T> Slice for [T] {
 
 pub enum Void {}
 
+// The function pointer type without a calling convention that the
+// trampoline pointers of a slice stand for, implemented next to `FnPointer`
+// for each shape of arguments: `ByValue`, `ByRef` or `ByMut` per argument.
+pub trait Trampoline<Shape> {
+    type Signature;
+}
+
+pub enum ByValue {}
+
+pub enum ByRef {}
+
+pub enum ByMut {}
+
+// Checks the copies of an element against the slice, through the slice
+// itself so that imported and renamed slices work. `(&SLICE).typecheck()`
+// finds the pointers the slice holds: functions returning the objects for a
+// slice of `&'static dyn Trait`, the elements themselves otherwise. Where the
+// pointer type maps back to its signature, `CheckSignature` has the element
+// compare the signature it was written with to the one the slice expects, in
+// a closure that is never called, which reports mismatches on the element in
+// the terms of the declaration. Other pointer types fall back to
+// `CheckElement`, which compares the trampoline pointer. Either one infers
+// the arguments of `impl Trait` from the slice and returns the pointer to
+// register.
+pub struct Typecheck<E>(PhantomData<E>);
+
+pub trait ObjectSlice<T: ?Sized> {
+    fn typecheck(&self) -> Typecheck<trampoline!({} { fn() -> &'static T })>;
+}
+
+impl<T: ?Sized + 'static> ObjectSlice<T> for DistributedFnSlice<[&'static T]> {
+    fn typecheck(&self) -> Typecheck<trampoline!({} { fn() -> &'static T })> {
+        Typecheck(PhantomData)
+    }
+}
+
+pub trait FnSlice<E> {
+    fn typecheck(&self) -> Typecheck<E>;
+}
+
+impl<E> FnSlice<E> for &DistributedFnSlice<[E]> {
+    fn typecheck(&self) -> Typecheck<E> {
+        Typecheck(PhantomData)
+    }
+}
+
+pub trait CheckSignature<E: Trampoline<Shape>, Shape> {
+    fn signature(&self) -> E::Signature;
+    fn check<T>(&self, element: T) -> T;
+}
+
+impl<E: Trampoline<Shape>, Shape> CheckSignature<E, Shape> for Typecheck<E> {
+    fn signature(&self) -> E::Signature {
+        unreachable!()
+    }
+
+    fn check<T>(&self, element: T) -> T {
+        element
+    }
+}
+
+pub trait CheckElement<E> {
+    fn signature<S>(&self) -> S;
+    fn check(&self, element: E) -> E;
+}
+
+impl<E> CheckElement<E> for &Typecheck<E> {
+    fn signature<S>(&self) -> S {
+        unreachable!()
+    }
+
+    fn check(&self, element: E) -> E {
+        element
+    }
+}
+
+// Implemented for `dyn Trait` by `#[serde_registry]` on a trait. The impls
+// of the trait call it for `Self`, which registers the type and returns its
+// tag.
+#[cfg(feature = "serde")]
+pub trait Register<T> {
+    fn register() -> &'static str;
+}
+
 pub fn value<T>() -> T {
     panic!()
 }
+
+// Inserts the calling convention of the generated trampolines between the
+// `{ head }` and `{ tail }` tokens of a function item or function pointer
+// type, optionally handing the result to a callback macro. The convention has
+// to be an explicit non-C one so that LLVM leaves the signature of the
+// otherwise internal trampolines alone, and it has to exist on the target,
+// which is only known once this crate is compiled.
+#[cfg(target_arch = "x86_64")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __generic_linkme_trampoline {
+    ({ $($head:tt)* } { $($tail:tt)* }) => {
+        $($head)* extern "sysv64" $($tail)*
+    };
+    ($callback:path { $($head:tt)* } { $($tail:tt)* }) => {
+        $callback ! { $($head)* extern "sysv64" $($tail)* }
+    };
+}
+
+#[cfg(target_arch = "x86")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __generic_linkme_trampoline {
+    ({ $($head:tt)* } { $($tail:tt)* }) => {
+        $($head)* extern "fastcall" $($tail)*
+    };
+    ($callback:path { $($head:tt)* } { $($tail:tt)* }) => {
+        $callback ! { $($head)* extern "fastcall" $($tail)* }
+    };
+}
+
+// RISC-V has no alternative to the C convention. LLVM's fastcc only differs
+// from it there once the eight integer argument registers are exhausted.
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __generic_linkme_trampoline {
+    ({ $($head:tt)* } { $($tail:tt)* }) => {
+        $($head)* extern "C" $($tail)*
+    };
+    ($callback:path { $($head:tt)* } { $($tail:tt)* }) => {
+        $callback ! { $($head)* extern "C" $($tail)* }
+    };
+}
+
+pub use crate::__generic_linkme_trampoline as trampoline;
@@ -0,0 +1,216 @@
+use std::fmt::{self, Display};
+
+use crate::distributed_fn_slice::Insn;
+
+/// How disassembly located the elements of a slice, as returned by
+/// [`DistributedFnSlice::report`](crate::DistributedFnSlice::report).
+/// Offsets are relative to the start of the slice's section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisassemblyReport {
+    /// Name of the slice's static.
+    pub name: String,
+    /// Runtime address of the section.
+    pub address: usize,
+    /// Size of the section in bytes.
+    pub len: usize,
+    /// Function bodies in section order. Each element is one body.
+    pub bodies: Vec<BodyReport>,
+}
+
+/// One function body in the section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BodyReport {
+    /// Offset of the body from the start of the section.
+    pub offset: usize,
+    /// Size of the body in bytes.
+    pub len: usize,
+    /// The disassembled instructions of the body.
+    pub instructions: Vec<Instruction>,
+    /// Direct calls and jumps in the body, in order.
+    pub candidates: Vec<Candidate>,
+    /// Index into `candidates` of the target taken as the element.
+    pub chosen: Option<usize>,
+    /// Index into `candidates` of the call to the element's metadata
+    /// function, for elements with a name or position.
+    pub metadata: Option<usize>,
+    /// Index into `candidates` of the call to the key function of the
+    /// instantiation, for generic elements with a name or position.
+    pub key: Option<usize>,
+    /// Why `chosen` was taken.
+    pub reason: Reason,
+}
+
+/// One disassembled instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instruction {
+    /// Offset of the instruction from the start of the section.
+    pub offset: usize,
+    /// The encoded instruction.
+    pub bytes: Vec<u8>,
+    /// The instruction in assembly syntax.
+    pub text: String,
+}
+
+/// A direct call or jump in a body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    /// Offset of the call or jump instruction.
+    pub offset: usize,
+    /// Absolute address it goes to.
+    pub target: usize,
+}
+
+/// Why a body's target was chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Reason {
+    /// The body has a single direct call or jump out of the section.
+    Only,
+    /// The body has several; the element is the last one, the others copy
+    /// arguments or probe the stack before it.
+    Last,
+    /// The body has none and does not contribute an element.
+    NoCandidates,
+    /// The body is the metadata function of an element.
+    Metadata,
+    /// The body is the key function of an instantiation of an element.
+    Key,
+}
+
+impl DisassemblyReport {
+    pub(crate) fn new(name: &str, code: &[u8], bodies: &[Vec<Insn>]) -> DisassemblyReport {
+        let start = code.as_ptr() as u64;
+        let offset = |address: u64| (address - start) as usize;
+        let inside = start..start + code.len() as u64;
+        // The first call of a body into the section is to a metadata function,
+        // the second to a key function.
+        let mut metadata_fns = Vec::new();
+        let mut key_fns = Vec::new();
+        for body in bodies {
+            let mut calls = body
+                .iter()
+                .filter_map(|insn| insn.target)
+                .filter(|target| inside.contains(target));
+            metadata_fns.extend(calls.next());
+            key_fns.extend(calls.next());
+        }
+        let bodies = bodies
+            .iter()
+            .map(|body| {
+                let instructions: Vec<Instruction> = body
+                    .iter()
+                    .map(|insn| Instruction {
+                        offset: offset(insn.address),
+                        bytes: code[offset(insn.address)..][..insn.len].to_vec(),
+                        text: insn.text.clone(),
+                    })
+                    .collect();
+                let candidates: Vec<Candidate> = body
+                    .iter()
+                    .filter_map(|insn| {
+                        Some(Candidate {
+                            offset: offset(insn.address),
+                            target: insn.target? as usize,
+                        })
+                    })
+                    .collect();
+                let inside = |candidate: &Candidate| inside.contains(&(candidate.target as u64));
+                let outside: Vec<usize> = (0..candidates.len())
+                    .filter(|&i| !inside(&candidates[i]))
+                    .collect();
+                let mut calls = (0..candidates.len()).filter(|&i| inside(&candidates[i]));
+                let metadata = calls.next();
+                let key = calls.next();
+                let start = body.first().map(|insn| insn.address);
+                let (chosen, reason) = match outside[..] {
+                    _ if matches!(start, Some(start) if metadata_fns.contains(&start)) => {
+                        (None, Reason::Metadata)
+                    }
+                    _ if matches!(start, Some(start) if key_fns.contains(&start)) => {
+                        (None, Reason::Key)
+                    }
+                    [] => (None, Reason::NoCandidates),
+                    [only] => (Some(only), Reason::Only),
+                    [.., last] => (Some(last), Reason::Last),
+                };
+                let first = instructions.first().map_or(0, |insn| insn.offset);
+                let end = instructions
+                    .last()
+                    .map_or(0, |insn| insn.offset + insn.bytes.len());
+                BodyReport {
+                    offset: first,
+                    len: end - first,
+                    instructions,
+                    candidates,
+                    chosen,
+                    metadata,
+                    key,
+                    reason,
+                }
+            })
+            .collect();
+        DisassemblyReport {
+            name: name.to_owned(),
+            address: start as usize,
+            len: code.len(),
+            bodies,
+        }
+    }
+
+    /// The element targets, one per body that has one.
+    pub fn targets(&self) -> Vec<usize> {
+        self.bodies.iter()
+            .filter_map(|body| Some(body.candidates[body.chosen?].target))
+            .collect()
+    }
+}
+
+impl Display for DisassemblyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {} bytes at {:#x}, {} bodies", self.name, self.len, self.address, self.bodies.len())?;
+        for body in &self.bodies {
+            writeln!(f)?;
+            Display::fmt(body, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for BodyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "body at +{:#x}, {} bytes: ", self.offset, self.len)?;
+        match self.chosen {
+            Some(i) => writeln!(f, "element {:#x}, {}", self.candidates[i].target, self.reason)?,
+            None => writeln!(f, "{}", self.reason)?,
+        }
+        for insn in &self.instructions {
+            let bytes: Vec<String> = insn.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            write!(f, "  +{:<6x} {:<30} {}", insn.offset, bytes.join(" "), insn.text)?;
+            let candidate = self.candidates.iter().position(|c| c.offset == insn.offset);
+            match candidate {
+                Some(i) if Some(i) == self.chosen => writeln!(f, "    <- element")?,
+                Some(i) if Some(i) == self.metadata => writeln!(f, "    <- metadata")?,
+                Some(i) if Some(i) == self.key => writeln!(f, "    <- key")?,
+                Some(_) => writeln!(f, "    <- skipped")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Only => "the only direct call or jump",
+            Reason::Last => "the last of several direct calls and jumps",
+            Reason::NoCandidates => "no direct call or jump, not an element",
+            Reason::Metadata => "metadata of an element, not an element",
+            Reason::Key => "key of an instantiation, not an element",
+        })
+    }
+}
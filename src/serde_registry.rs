@@ -0,0 +1,137 @@
+//! Serialization of `Box<dyn Trait>` for traits whose implementors may be
+//! generic, declared with `#[serde_registry]` on the trait and on each impl.
+//!
+//! # Type tags are not stable
+//!
+//! A trait object is written as a map with a single entry, from the type tag
+//! of the implementor to its value. The tag is the
+//! [`type_name`](core::any::type_name) of the implementor, such as
+//! `my_crate::Envelope<u8>`, which the standard library gives no guarantees
+//! about: it may differ between compiler versions, and it changes when a type
+//! is moved or renamed. Data written by one build is only certain to be read
+//! back by the same build, so the registry is not suited for files or
+//! messages exchanged between programs built separately.
+
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserializer, Serializer};
+
+/// Implemented for `dyn Trait` by `#[serde_registry]` on a trait, which
+/// declares a distributed slice that every instantiation of an impl of the
+/// trait adds its registration to.
+pub trait Registry {
+    /// The name of the trait, for error messages.
+    const NAME: &'static str;
+
+    /// The registrations of the linked implementors, possibly repeated when
+    /// several crates instantiate the same impl.
+    fn registrations() -> Vec<Registration<Self>>;
+}
+
+/// The type tag of an implementor of a registry's trait together with the
+/// function that deserializes it into a boxed trait object.
+pub struct Registration<R: ?Sized> {
+    tag: &'static str,
+    deserialize: fn(&mut dyn erased_serde::Deserializer) -> Result<Box<R>, erased_serde::Error>,
+}
+
+impl<R: ?Sized> Registration<R> {
+    #[doc(hidden)]
+    pub fn private_new(
+        tag: &'static str,
+        deserialize: fn(&mut dyn erased_serde::Deserializer) -> Result<Box<R>, erased_serde::Error>,
+    ) -> Self {
+        Registration { tag, deserialize }
+    }
+
+    /// The type tag that values of the implementor are written with.
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+}
+
+/// The tags registered for a trait, sorted and without duplicates.
+pub fn tags<R: ?Sized + Registry>() -> Vec<&'static str> {
+    let mut tags: Vec<&'static str> = R::registrations().iter().map(Registration::tag).collect();
+    tags.sort_unstable();
+    tags.dedup();
+    tags
+}
+
+// Trait objects are written as a map with a single entry, from the type tag
+// to the value.
+#[doc(hidden)]
+pub fn serialize<S: Serializer>(
+    tag: &'static str,
+    value: &dyn erased_serde::Serialize,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry(tag, value)?;
+    map.end()
+}
+
+#[doc(hidden)]
+pub fn deserialize<'de, R, D>(deserializer: D) -> Result<Box<R>, D::Error>
+where
+    R: ?Sized + Registry,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(TaggedVisitor(PhantomData))
+}
+
+struct TaggedVisitor<R: ?Sized>(PhantomData<fn() -> Box<R>>);
+
+impl<'de, R: ?Sized + Registry> Visitor<'de> for TaggedVisitor<R> {
+    type Value = Box<R>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a map from a {} type tag to the value", R::NAME)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Box<R>, A::Error> {
+        let tag: String = match map.next_key()? {
+            Some(tag) => tag,
+            None => return Err(de::Error::custom(format_args!("missing {} type tag", R::NAME))),
+        };
+        let registrations = R::registrations();
+        let registration = match registrations.iter().find(|registration| registration.tag == tag) {
+            Some(registration) => registration,
+            None => return Err(de::Error::custom(unknown_tag::<R>(&tag))),
+        };
+        let value = map.next_value_seed(registration)?;
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom(format_args!(
+                "expected a single {} type tag",
+                R::NAME,
+            )));
+        }
+        Ok(value)
+    }
+}
+
+// The value of the entry is deserialized straight into the registered type.
+impl<'de, R: ?Sized> DeserializeSeed<'de> for &Registration<R> {
+    type Value = Box<R>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Box<R>, D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.deserialize)(&mut erased).map_err(de::Error::custom)
+    }
+}
+
+fn unknown_tag<R: ?Sized + Registry>(tag: &str) -> String {
+    let tags = tags::<R>();
+    if tags.is_empty() {
+        return format!("unknown {} type tag `{}`, no types are registered", R::NAME, tag);
+    }
+    let known: Vec<String> = tags.iter().map(|tag| format!("`{}`", tag)).collect();
+    format!(
+        "unknown {} type tag `{}`, expected one of {}",
+        R::NAME,
+        tag,
+        known.join(", "),
+    )
+}
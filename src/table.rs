@@ -0,0 +1,59 @@
+use core::ptr;
+
+/// Number of elements a table has room for.
+pub(crate) const CAPACITY: usize = 254;
+
+pub(crate) const UNRESOLVED: u32 = u32::from_le_bytes(*b"GLT0");
+pub(crate) const RESOLVED: u32 = u32::from_le_bytes(*b"GLT3");
+
+/// Metadata or key offset of an element that has none.
+pub(crate) const NO_METADATA: i32 = i32::MIN;
+
+/// Offsets of the target of an element and of its metadata and key functions.
+pub(crate) type Offsets = (isize, Option<isize>, Option<isize>);
+
+// Companion of every slice when the `postlink` feature is enabled, reserved in
+// `generic_linkme_tbl_<NAME>` on ELF targets. It is left unresolved by the compiler and filled in after linking
+// by `postlink::patch` with the offsets of the elements' targets, and of the
+// metadata and key functions their bodies call, relative to the start of
+// `generic_linkme_<NAME>`. The magic is non-zero so that the section has file
+// contents to patch.
+#[repr(C)]
+pub struct Table {
+    magic: u32,
+    len: u32,
+    offsets: [i32; CAPACITY],
+    metadata: [i32; CAPACITY],
+    keys: [i32; CAPACITY],
+}
+
+impl Table {
+    pub const EMPTY: Table = Table {
+        magic: UNRESOLVED,
+        len: 0,
+        offsets: [0; CAPACITY],
+        metadata: [NO_METADATA; CAPACITY],
+        keys: [NO_METADATA; CAPACITY],
+    };
+
+    /// Offsets of the targets, metadata and key functions written by the
+    /// post-link step, or `None` if it has not run.
+    pub(crate) fn entries(&self) -> Option<Vec<Offsets>> {
+        // The compiler only ever sees `EMPTY`, so every read has to be
+        // volatile or it would be folded to the initial value.
+        let magic = unsafe { ptr::read_volatile(&self.magic) };
+        let len = unsafe { ptr::read_volatile(&self.len) } as usize;
+        if magic != RESOLVED || len > CAPACITY {
+            return None;
+        }
+        Some((0..len)
+            .map(|i| {
+                let offset = unsafe { ptr::read_volatile(&self.offsets[i]) };
+                let metadata = unsafe { ptr::read_volatile(&self.metadata[i]) };
+                let key = unsafe { ptr::read_volatile(&self.keys[i]) };
+                let function = |offset: i32| Some(offset).filter(|&o| o != NO_METADATA).map(|o| o as isize);
+                (offset as isize, function(metadata), function(key))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,70 @@
+use core::any::{type_name, TypeId};
+use core::mem;
+use once_cell::sync::OnceCell;
+
+/// The types that [`register_type`] is instantiated with, exported as
+/// `TYPES`. [`TypeInfo::all`] lists them without duplicates.
+//
+// The section is named after the static, which would otherwise clash with a
+// slice of that name declared by a user of the crate.
+#[crate::distributed_fn_slice]
+#[linkme(crate = crate)]
+#[allow(improper_ctypes_definitions)]
+pub static GENERIC_LINKME_TYPES: [fn() -> TypeInfo] = [..];
+
+/// The name and layout of a registered type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeInfo {
+    /// The [`type_name`] of the type.
+    pub name: &'static str,
+    pub type_id: TypeId,
+    /// The size of the type in bytes.
+    pub size: usize,
+    /// The alignment of the type in bytes.
+    pub align: usize,
+    /// Whether dropping a value of the type runs any code.
+    pub needs_drop: bool,
+}
+
+/// Every instantiation of it adds the layout of its type argument to
+/// [`TYPES`](crate::TYPES), whether it is called or only linked.
+#[crate::distributed_fn_slice(GENERIC_LINKME_TYPES)]
+#[linkme(crate = crate)]
+pub fn register_type<T: 'static>() -> TypeInfo {
+    TypeInfo {
+        name: type_name::<T>(),
+        type_id: TypeId::of::<T>(),
+        size: mem::size_of::<T>(),
+        align: mem::align_of::<T>(),
+        needs_drop: mem::needs_drop::<T>(),
+    }
+}
+
+impl TypeInfo {
+    /// The registered types ordered by name, each once, even if several
+    /// crates instantiate the element for it.
+    pub fn all() -> &'static [TypeInfo] {
+        static ALL: OnceCell<Vec<TypeInfo>> = OnceCell::new();
+        ALL.get_or_init(|| {
+            let mut all: Vec<TypeInfo> = GENERIC_LINKME_TYPES.iter().map(|info| info()).collect();
+            all.sort_by(|a, b| (a.name, a.type_id).cmp(&(b.name, b.type_id)));
+            all.dedup_by_key(|info| info.type_id);
+            all
+        })
+    }
+
+    /// The registered type with the given id.
+    pub fn by_id(type_id: TypeId) -> Option<&'static TypeInfo> {
+        TypeInfo::all().iter().find(|info| info.type_id == type_id)
+    }
+
+    /// The registered type with the given name.
+    ///
+    /// Type names are not unique, for example across lifetimes or crate
+    /// versions, in which case this is one of the matching types.
+    pub fn by_name(name: &str) -> Option<&'static TypeInfo> {
+        let all = TypeInfo::all();
+        let index = all.partition_point(|info| info.name < name);
+        all.get(index).filter(|info| info.name == name)
+    }
+}
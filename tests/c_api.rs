@@ -0,0 +1,76 @@
+#![cfg(feature = "c_api")]
+
+use generic_linkme::c_api::{self, generic_linkme_registry, generic_linkme_registry_get};
+use generic_linkme::c_api::{generic_linkme_registry_len, generic_linkme_registry_name};
+use generic_linkme::{c_registry, distributed_fn_slice, link};
+use generic_linkme::c_api::Registry;
+use std::ffi::{c_char, CStr, CString};
+
+#[c_registry("handlers")]
+#[distributed_fn_slice]
+pub static HANDLERS: [fn(i32, *const c_char) -> i32] = [..];
+
+#[distributed_fn_slice(HANDLERS, name = "double")]
+fn double(x: i32, _tag: *const c_char) -> i32 {
+    2 * x
+}
+
+#[distributed_fn_slice(HANDLERS, pos = 0)]
+fn negate(x: i32, _tag: *const c_char) -> i32 {
+    -x
+}
+
+#[c_registry("callbacks")]
+#[distributed_fn_slice]
+pub static CALLBACKS: [fn(*mut *const c_char, usize) -> bool] = [..];
+
+fn registry(name: &str) -> Option<&'static Registry> {
+    let name = CString::new(name).unwrap();
+    unsafe { generic_linkme_registry(name.as_ptr()) }
+}
+
+fn link_handlers() {
+    link(double);
+    link(negate);
+}
+
+#[test]
+fn enumerate_from_c_api() {
+    let handlers = registry("handlers");
+    assert!(handlers.is_some());
+    assert_eq!(generic_linkme_registry_len(handlers), 2);
+    assert!(generic_linkme_registry_name(handlers, 0).is_null());
+    let name = unsafe { CStr::from_ptr(generic_linkme_registry_name(handlers, 1)) };
+    assert_eq!(name.to_str().unwrap(), "double");
+    assert!(generic_linkme_registry_get(handlers, 2).is_null());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let handler: extern "sysv64" fn(i32, *const c_char) -> i32 =
+            unsafe { std::mem::transmute(generic_linkme_registry_get(handlers, 1)) };
+        assert_eq!(handler(21, std::ptr::null()), 42);
+    }
+
+    let callbacks = registry("callbacks");
+    assert_eq!(generic_linkme_registry_len(callbacks), 0);
+    assert!(registry("missing").is_none());
+    assert_eq!(generic_linkme_registry_len(None), 0);
+    link_handlers();
+}
+
+#[test]
+fn header_declares_element_types() {
+    let names: Vec<_> = c_api::registries().unwrap().iter().map(|registry| registry.name()).collect();
+    assert_eq!(names, ["callbacks", "handlers"]);
+    let header = c_api::header().unwrap();
+    assert!(header.contains("size_t generic_linkme_registry_len(const struct generic_linkme_registry *registry);\n"));
+    #[cfg(target_arch = "x86_64")]
+    {
+        assert!(header.contains(
+            "/* Elements of the registry \"handlers\". */\n\
+             typedef int32_t (__attribute__((sysv_abi)) *generic_linkme_handlers_fn)(int32_t, const char *);\n",
+        ));
+        assert!(header.contains("typedef bool (__attribute__((sysv_abi)) *generic_linkme_callbacks_fn)(const char **, size_t);\n"));
+    }
+    link_handlers();
+}
@@ -0,0 +1,27 @@
+#![cfg(feature = "c_api")]
+
+use generic_linkme::c_api::{self, generic_linkme_registry};
+use generic_linkme::{c_registry, distributed_fn_slice};
+use std::ffi::CString;
+
+#[c_registry("handlers")]
+#[distributed_fn_slice]
+pub static HANDLERS: [fn(i32) -> i32] = [..];
+
+#[c_registry("handlers")]
+#[distributed_fn_slice]
+pub static OTHER_HANDLERS: [fn(u32) -> u32] = [..];
+
+#[c_registry("callbacks")]
+#[distributed_fn_slice]
+pub static CALLBACKS: [fn()] = [..];
+
+#[test]
+fn same_name_twice() {
+    let conflict = c_api::registries().err().unwrap();
+    assert_eq!(conflict.name, "handlers");
+    assert_eq!(conflict.to_string(), "more than one slice is exported as `handlers`");
+    assert_eq!(c_api::header().unwrap_err(), conflict);
+    let name = CString::new("callbacks").unwrap();
+    assert!(unsafe { generic_linkme_registry(name.as_ptr()) }.is_none());
+}
@@ -0,0 +1,115 @@
+use generic_linkme::commands::{self, Command};
+use generic_linkme::{command, distributed_fn_slice, link};
+
+#[distributed_fn_slice]
+pub static COMMANDS: [fn() -> Command] = [..];
+
+pub trait Format {
+    const EXTENSION: &'static str;
+}
+
+pub struct Json;
+pub struct JsonLines;
+pub struct CSV;
+
+impl Format for Json {
+    const EXTENSION: &'static str = "json";
+}
+
+impl Format for JsonLines {
+    const EXTENSION: &'static str = "jsonl";
+}
+
+impl Format for CSV {
+    const EXTENSION: &'static str = "csv";
+}
+
+/// Exports the records
+/// to a file.
+///
+/// Takes the path of the file.
+#[command(name = "export", slice = COMMANDS)]
+fn export<F: Format>(args: &[String]) -> i32 {
+    match args {
+        [path] if path.ends_with(F::EXTENSION) => 0,
+        _ => 1,
+    }
+}
+
+/// Checks the records.
+#[command(slice = COMMANDS)]
+fn self_check(args: &[String]) -> i32 {
+    args.len() as i32 + 10
+}
+
+#[distributed_fn_slice]
+pub static CLASHING: [fn() -> Command] = [..];
+
+/// Imports the records.
+#[command(name = "import", slice = CLASHING)]
+fn import(_args: &[String]) -> i32 {
+    0
+}
+
+/// Imports the records again.
+#[command(name = "import", slice = CLASHING)]
+fn reimport(_args: &[String]) -> i32 {
+    1
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|&arg| arg.to_owned()).collect()
+}
+
+fn link_commands() {
+    link(export::<Json>);
+    link(export::<JsonLines>);
+    link(export::<CSV>);
+}
+
+#[test]
+fn subcommand_per_instantiation() {
+    let names: Vec<_> = commands::list(&COMMANDS).unwrap().into_iter().map(|command| command.name).collect();
+    assert_eq!(names, ["export-csv", "export-json", "export-json-lines", "self-check"]);
+    let entry = COMMANDS.describe(0).unwrap();
+    assert!(matches!(entry.name(), Some("export" | "self-check")));
+    link_commands();
+}
+
+#[test]
+fn help_from_doc_comment() {
+    let list = commands::list(&COMMANDS).unwrap();
+    assert_eq!(list[0].help, "Exports the records\nto a file.\n\nTakes the path of the file.");
+    assert_eq!(list[0].summary(), "Exports the records to a file.");
+    assert_eq!(
+        commands::usage(&COMMANDS, "tool").unwrap(),
+        "usage: tool <command> [args...]\n\
+         \n\
+         commands:\n  \
+           export-csv         Exports the records to a file.\n  \
+           export-json        Exports the records to a file.\n  \
+           export-json-lines  Exports the records to a file.\n  \
+           self-check         Checks the records.\n  \
+           help               Print this message or the help of a command\n",
+    );
+    link_commands();
+}
+
+#[test]
+fn dispatch() {
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "export-json", "out.json"])), 0);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "export-csv", "out.json"])), 1);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "export-json-lines", "a.jsonl"])), 0);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "self-check", "a", "b"])), 12);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "help", "export-csv"])), 0);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool", "import"])), 2);
+    assert_eq!(commands::dispatch(&COMMANDS, &argv(&["tool"])), 2);
+    link_commands();
+}
+
+#[test]
+fn clashing_names() {
+    let clash = commands::list(&CLASHING).unwrap_err();
+    assert_eq!(clash.to_string(), "more than one command is named `import`");
+    assert_eq!(commands::dispatch(&CLASHING, &argv(&["tool", "import"])), 2);
+}
@@ -0,0 +1,6 @@
+#[rustversion::attr(not(nightly), ignore = "requires nightly")]
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,169 @@
+use generic_linkme::di::{Ambiguous, Container, Provider, ResolveError};
+use generic_linkme::{distributed_fn_slice, link, provider};
+use std::any::{type_name, Any};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[distributed_fn_slice]
+pub static PROVIDERS: [fn() -> Provider] = [..];
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Config {
+    pub url: String,
+}
+
+#[derive(Default)]
+pub struct Metrics;
+
+pub struct Database {
+    pub config: Arc<Config>,
+}
+
+pub struct Request {
+    pub id: usize,
+}
+
+#[derive(Debug)]
+pub struct Ping;
+pub struct Pong;
+
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+#[provider(PROVIDERS)]
+fn default<T: Default + Send + Sync + 'static>() -> T {
+    T::default()
+}
+
+#[provider(PROVIDERS)]
+fn database(container: &Container) -> Database {
+    Database {
+        config: container.get::<Config>(),
+    }
+}
+
+#[provider(PROVIDERS, transient)]
+fn request() -> Request {
+    Request {
+        id: REQUESTS.fetch_add(1, Ordering::SeqCst),
+    }
+}
+
+#[provider(PROVIDERS)]
+fn ping(container: &Container) -> Ping {
+    container.get::<Pong>();
+    Ping
+}
+
+#[provider(PROVIDERS)]
+fn pong(container: &Container) -> Pong {
+    container.get::<Ping>();
+    Pong
+}
+
+#[distributed_fn_slice]
+pub static AMBIGUOUS: [fn() -> Provider] = [..];
+
+#[provider(AMBIGUOUS)]
+fn local_config() -> Config {
+    Config { url: "localhost".to_owned() }
+}
+
+#[provider(AMBIGUOUS)]
+fn remote_config() -> Config {
+    Config { url: "example.com".to_owned() }
+}
+
+#[distributed_fn_slice]
+pub static FACTORIES: [fn() -> Provider] = [..];
+
+#[provider(FACTORIES)]
+fn provide<T: Default + 'static>() -> Box<dyn Any> {
+    Box::new(T::default())
+}
+
+fn link_providers() {
+    link(default::<Config>);
+    link(default::<Metrics>);
+}
+
+#[test]
+fn singletons_and_dependencies() {
+    let container = Container::new(&PROVIDERS).unwrap();
+    let config = container.resolve::<Config>().unwrap();
+    assert_eq!(*config, Config::default());
+    let database = container.resolve::<Database>().unwrap();
+    assert!(Arc::ptr_eq(&database.config, &config));
+    assert!(Arc::ptr_eq(&container.resolve::<Database>().unwrap(), &database));
+
+    // Singletons belong to their container.
+    let other = Container::new(&PROVIDERS).unwrap();
+    assert!(!Arc::ptr_eq(&other.resolve::<Config>().unwrap(), &config));
+    link_providers();
+}
+
+#[test]
+fn boxed_any_factory() {
+    let container = Container::new(&FACTORIES).unwrap();
+    assert_eq!(*container.resolve::<Config>().unwrap(), Config::default());
+    assert_eq!(*container.resolve::<u32>().unwrap(), 0);
+    let provided: Vec<_> = container.provided().iter().map(|info| info.name).collect();
+    assert_eq!(provided, [type_name::<Config>(), "u32"]);
+    link(provide::<Config>);
+    link(provide::<u32>);
+}
+
+#[test]
+fn transient() {
+    let container = Container::new(&PROVIDERS).unwrap();
+    let first = container.resolve::<Request>().unwrap();
+    let second = container.resolve::<Request>().unwrap();
+    assert_ne!(first.id, second.id);
+    link_providers();
+}
+
+#[test]
+fn missing_lists_available() {
+    let container = Container::new(&PROVIDERS).unwrap();
+    let err = container.resolve::<String>().err().unwrap();
+    let available = [
+        type_name::<Config>(),
+        type_name::<Database>(),
+        type_name::<Metrics>(),
+        type_name::<Ping>(),
+        type_name::<Pong>(),
+        type_name::<Request>(),
+    ];
+    assert_eq!(
+        err,
+        ResolveError::Missing {
+            type_name: "alloc::string::String",
+            available: available.to_vec(),
+        },
+    );
+    assert_eq!(
+        err.to_string(),
+        format!("no provider for `alloc::string::String`, available: `{}`", available.join("`, `")),
+    );
+    link_providers();
+}
+
+#[test]
+fn cycle() {
+    let container = Container::new(&PROVIDERS).unwrap();
+    let panic = std::panic::catch_unwind(|| container.resolve::<Ping>()).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        *message,
+        format!("cycle between providers: `{0}` -> `{1}` -> `{0}`", type_name::<Ping>(), type_name::<Pong>()),
+    );
+    // The failed resolution leaves nothing behind.
+    assert!(container.resolve::<Config>().is_ok());
+    link_providers();
+}
+
+#[test]
+fn ambiguous() {
+    let err = Container::new(&AMBIGUOUS).err().unwrap();
+    assert_eq!(err, Ambiguous { type_name: type_name::<Config>() });
+    assert_eq!(err.to_string(), format!("more than one provider for `{}`", type_name::<Config>()));
+}
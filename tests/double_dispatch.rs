@@ -0,0 +1,85 @@
+use generic_linkme::dispatch::{self, dispatch2, Binary};
+use generic_linkme::{distributed_fn_slice, double_dispatch, link};
+use std::any::{type_name, Any, TypeId};
+
+pub trait Shape: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn area(&self) -> f64;
+}
+
+pub struct Circle(f64);
+pub struct Square(f64);
+pub struct Point;
+
+impl Shape for Circle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn area(&self) -> f64 {
+        3.0 * self.0 * self.0
+    }
+}
+
+impl Shape for Square {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+}
+
+impl Shape for Point {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn area(&self) -> f64 {
+        0.0
+    }
+}
+
+#[distributed_fn_slice]
+pub static COLLIDE: [fn() -> Binary<String>] = [..];
+
+#[double_dispatch(COLLIDE)]
+fn collide<A: Shape, B: Shape>(a: &A, b: &B) -> String {
+    let short = |name: &'static str| name.rsplit("::").next().unwrap();
+    format!("{} {} / {} {}", short(type_name::<A>()), a.area(), short(type_name::<B>()), b.area())
+}
+
+fn collide_dyn(a: &dyn Shape, b: &dyn Shape) -> Option<String> {
+    dispatch2(&COLLIDE, a.as_any(), b.as_any())
+}
+
+fn link_collisions() {
+    link(collide::<Circle, Square>);
+    link(collide::<Square, Square>);
+    link(collide::<Square, Circle>);
+    link(collide::<Circle, Point>);
+}
+
+#[test]
+fn dispatch_on_both_types() {
+    let shapes: [Box<dyn Shape>; 3] = [Box::new(Circle(1.0)), Box::new(Square(2.0)), Box::new(Point)];
+    assert_eq!(collide_dyn(&*shapes[0], &*shapes[1]).unwrap(), "Circle 3 / Square 4");
+    assert_eq!(collide_dyn(&*shapes[1], &*shapes[0]).unwrap(), "Square 4 / Circle 3");
+    assert_eq!(collide_dyn(&*shapes[1], &*shapes[1]).unwrap(), "Square 4 / Square 4");
+    assert_eq!(collide_dyn(&*shapes[0], &*shapes[2]).unwrap(), "Circle 3 / Point 0");
+    assert_eq!(collide_dyn(&*shapes[2], &*shapes[0]), None);
+    assert_eq!(collide_dyn(&*shapes[0], &*shapes[0]), None);
+    link_collisions();
+}
+
+#[test]
+fn table_keyed_by_type_ids() {
+    let table = dispatch::table(&COLLIDE);
+    assert_eq!(table.len(), 4);
+    let binary = &table[&(TypeId::of::<Circle>(), TypeId::of::<Point>())];
+    assert_eq!(binary.left.name, type_name::<Circle>());
+    assert_eq!(binary.right.name, type_name::<Point>());
+    assert_eq!(binary.right.size, 0);
+    link_collisions();
+}
@@ -0,0 +1,58 @@
+#![allow(clippy::toplevel_ref_arg)]
+
+use generic_linkme::{distributed_fn_slice, link};
+use std::mem::size_of;
+
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+#[distributed_fn_slice]
+pub static SUMS: [fn((u32, u32), &Point, u32) -> u32] = [..];
+
+#[distributed_fn_slice(SUMS)]
+fn destructured<T>((a, b): (u32, u32), &Point { x, y }: &Point, _: u32) -> u32 {
+    a + b + x + y + size_of::<T>() as u32
+}
+
+#[distributed_fn_slice(SUMS)]
+fn bindings<T>(ref pair: (u32, u32), point @ &Point { .. }: &Point, mut extra: u32) -> u32 {
+    extra *= size_of::<T>() as u32;
+    pair.0 * pair.1 + point.x * point.y + extra
+}
+
+pub trait Format {
+    fn format(&self) -> String;
+}
+
+impl Format for u32 {
+    fn format(&self) -> String {
+        format!("u32 {}", self)
+    }
+}
+
+#[distributed_fn_slice]
+pub static PRINTERS: [fn(u32, &str) -> String] = [..];
+
+#[distributed_fn_slice(PRINTERS)]
+fn print<T>(value: impl Format, suffix: &(impl AsRef<str> + ?Sized)) -> String {
+    format!("{}{} {}", value.format(), suffix.as_ref(), size_of::<T>())
+}
+
+#[test]
+fn patterns() {
+    let point = Point { x: 3, y: 4 };
+    let mut v: Vec<u32> = SUMS.iter().map(|f| f((1, 2), &point, 5)).collect();
+    v.sort_unstable();
+    assert_eq!(v, [12, 14 + 20]);
+    link(destructured::<u16>);
+    link(bindings::<u32>);
+}
+
+#[test]
+fn impl_trait_arguments() {
+    let v: Vec<String> = PRINTERS.iter().map(|f| f(7, "!")).collect();
+    assert_eq!(v, ["u32 7! 8"]);
+    assert_eq!(print::<u64>(7u32, "?"), "u32 7? 8");
+}
@@ -0,0 +1,60 @@
+use generic_linkme::{distributed_fn_slice, link, FnPointer};
+
+pub type Weigh = fn(u32, &'static str) -> u32;
+
+#[distributed_fn_slice]
+pub static WEIGHTS: [Weigh] = [..];
+
+#[distributed_fn_slice(WEIGHTS)]
+fn weigh<T>(count: u32, unit: &'static str) -> u32 {
+    count * std::mem::size_of::<T>() as u32 + unit.len() as u32
+}
+
+pub struct Ctx(usize);
+
+pub type Handler = fn(&Ctx, &mut Vec<usize>);
+
+#[distributed_fn_slice]
+pub static HANDLERS: [Handler] = [..];
+
+#[distributed_fn_slice(HANDLERS)]
+fn handle<T>(ctx: &Ctx, out: &mut Vec<usize>) {
+    out.push(ctx.0 * std::mem::size_of::<T>());
+}
+
+#[derive(Clone, Copy, FnPointer)]
+#[repr(transparent)]
+pub struct Parser(fn(&str) -> Option<u64>);
+
+#[distributed_fn_slice]
+pub static PARSERS: [Parser] = [..];
+
+#[distributed_fn_slice(PARSERS)]
+fn parse<T>(input: &str) -> Option<u64> {
+    input.parse::<u64>().ok().map(|n| n * std::mem::size_of::<T>() as u64)
+}
+
+#[test]
+fn type_alias() {
+    let weights: Vec<u32> = WEIGHTS.iter().map(|weigh| weigh(3, "kg")).collect();
+    assert_eq!(weights, [14]);
+    link(weigh::<u32>);
+}
+
+#[test]
+fn elided_lifetime_alias() {
+    let mut out = Vec::new();
+    for handler in &HANDLERS {
+        handler(&Ctx(5), &mut out);
+    }
+    assert_eq!(out, [40]);
+    link(handle::<u64>);
+}
+
+#[test]
+fn transparent_newtype() {
+    let parsed: Vec<_> = PARSERS.iter().map(|parser| parser("21")).collect();
+    assert_eq!(parsed, [Some(42)]);
+    assert_eq!(PARSERS[0]("x"), None);
+    link(parse::<u16>);
+}
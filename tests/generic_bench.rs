@@ -0,0 +1,29 @@
+use generic_linkme::generic_bench;
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[generic_bench(types = [Vec<u64>, HashSet<u64>, BTreeSet<u64>])]
+fn collect<C: FromIterator<u64>>() -> C {
+    RUNS.fetch_add(1, Ordering::Relaxed);
+    (0..64).collect()
+}
+
+#[generic_bench(types = [u32, u64])]
+fn checked_sum<T: Copy + From<u8> + std::ops::Add<Output = T>>() {
+    RUNS.fetch_add(1, Ordering::Relaxed);
+    let mut sum = T::from(0);
+    for i in 0..64u8 {
+        sum = sum + T::from(i);
+    }
+    std::hint::black_box(sum);
+}
+
+fn main() {
+    generic_linkme::bench_main();
+    if env::args().len() == 1 {
+        assert_eq!(RUNS.load(Ordering::Relaxed), 5);
+    }
+}
@@ -0,0 +1,149 @@
+use generic_linkme::generic_test;
+use std::env;
+use std::fmt::Debug;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+pub trait Codec: Sized + Debug + PartialEq {
+    fn sample() -> Self;
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+impl Codec for u8 {
+    fn sample() -> Self {
+        7
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![*self]
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+
+impl Codec for String {
+    fn sample() -> Self {
+        "seven".to_owned()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+}
+
+impl Codec for Vec<u32> {
+    fn sample() -> Self {
+        vec![7, 8]
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.iter().flat_map(|n| n.to_le_bytes()).collect()
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        bytes.chunks(4).map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap())).collect()
+    }
+}
+
+#[generic_test(types = [u8, String, Vec<u32>])]
+fn roundtrip<T: Codec>() {
+    let value = T::sample();
+    assert_eq!(T::decode(&value.encode()), value);
+    RUNS.fetch_add(1, Ordering::Relaxed);
+}
+
+mod nested {
+    use super::RUNS;
+    use generic_linkme::generic_test;
+    use std::sync::atomic::Ordering;
+
+    #[generic_test(types = [u16])]
+    fn default_is_zero<T: Default + PartialEq + From<u8>>() {
+        assert!(T::default() == T::from(0));
+        RUNS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Fails when this binary runs itself to check how failures are reported.
+#[generic_test(types = [u32])]
+fn fails_on_request<T: Default + PartialEq>() {
+    assert!(env::var_os("GENERIC_LINKME_FAIL").is_none());
+    assert!(T::default() == T::default());
+}
+
+// Runs this binary again with `args`, for its output and exit code.
+fn rerun(args: &[&str], fail: bool) -> (String, String, i32) {
+    let mut command = Command::new(env::current_exe().unwrap());
+    command.args(args);
+    if fail {
+        command.env("GENERIC_LINKME_FAIL", "1");
+    }
+    let output = command.output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (stdout, stderr, output.status.code().unwrap())
+}
+
+fn check_command_line() {
+    let (stdout, _, code) = rerun(&["--list"], false);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "fails_on_request::<u32>: test\n\
+         nested::default_is_zero::<u16>: test\n\
+         roundtrip::<alloc::string::String>: test\n\
+         roundtrip::<alloc::vec::Vec<u32>>: test\n\
+         roundtrip::<u8>: test\n\
+         \n\
+         5 tests, 0 benchmarks\n",
+    );
+
+    let (stdout, _, code) = rerun(&["roundtrip", "--test-threads=1"], false);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("\nrunning 3 tests\n"));
+    assert!(stdout.contains("test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 2 filtered out;"));
+
+    let (stdout, _, _) = rerun(&["--skip", "roundtrip", "--skip=fails"], false);
+    assert!(stdout.contains("\nrunning 1 test\ntest nested::default_is_zero::<u16> ... ok\n"));
+    assert!(stdout.contains("1 passed; 0 failed; 0 ignored; 0 measured; 4 filtered out;"));
+
+    let (stdout, _, _) = rerun(&["--exact", "roundtrip::<u8>"], false);
+    assert!(stdout.contains("\nrunning 1 test\ntest roundtrip::<u8> ... ok\n"));
+    let (stdout, _, _) = rerun(&["--exact", "roundtrip"], false);
+    assert!(stdout.contains("\nrunning 0 tests\n"));
+
+    let (stdout, _, code) = rerun(&["--ignored"], false);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("\nrunning 0 tests\n"));
+    assert!(stdout.contains("0 passed; 0 failed; 0 ignored; 0 measured; 5 filtered out;"));
+    let (stdout, _, _) = rerun(&["--include-ignored", "nested"], false);
+    assert!(stdout.contains("\nrunning 1 test\n"));
+
+    let (stdout, stderr, code) = rerun(&["--frobnicate"], false);
+    assert_eq!(code, 101);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "error: Unrecognized option: '--frobnicate'\n");
+
+    let (stdout, _, code) = rerun(&["fails_on_request"], true);
+    assert_eq!(code, 101);
+    assert!(stdout.contains("\ntest fails_on_request::<u32> ... FAILED\n"));
+    assert!(stdout.contains("\nfailures:\n    fails_on_request::<u32>\n"));
+    assert!(stdout.contains("test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 4 filtered out;"));
+}
+
+fn main() {
+    generic_linkme::test_main();
+    if env::args().len() == 1 {
+        assert_eq!(RUNS.load(Ordering::Relaxed), 4);
+        check_command_line();
+    }
+}
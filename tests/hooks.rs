@@ -0,0 +1,100 @@
+use generic_linkme::hooks::{self, HookError};
+use generic_linkme::{distributed_fn_slice, hook, link};
+use std::any::type_name;
+use std::sync::Mutex;
+
+pub struct Ctx {
+    pub log: Mutex<Vec<String>>,
+}
+
+pub trait Store {
+    const NAME: &'static str;
+}
+
+pub struct Disk;
+pub struct Memory;
+
+impl Store for Disk {
+    const NAME: &'static str = "disk";
+}
+
+impl Store for Memory {
+    const NAME: &'static str = "memory";
+}
+
+#[distributed_fn_slice]
+pub static ON_SHUTDOWN: [fn(&Ctx) -> Result<(), String>] = [..];
+
+#[hook(ON_SHUTDOWN, priority = 10)]
+fn flush<T: Store>(ctx: &Ctx) -> Result<(), String> {
+    ctx.log.lock().unwrap().push(format!("flush {}", T::NAME));
+    Ok(())
+}
+
+#[hook(ON_SHUTDOWN, priority = 5, name = "close")]
+fn close_connections<T>(ctx: &Ctx) -> Result<(), String> {
+    ctx.log.lock().unwrap().push("close".to_owned());
+    Err(format!("{} still open", type_name::<T>()))
+}
+
+#[hook(ON_SHUTDOWN, priority = 10)]
+fn audit<T>(ctx: &Ctx) -> Result<(), String> {
+    ctx.log.lock().unwrap().push("audit".to_owned());
+    panic!("audit log unavailable");
+}
+
+#[hook(ON_SHUTDOWN)]
+fn goodbye<T>(ctx: &Ctx) -> Result<(), String> {
+    ctx.log.lock().unwrap().push("goodbye".to_owned());
+    Ok(())
+}
+
+#[distributed_fn_slice]
+pub static ON_START: [fn(&str)] = [..];
+
+#[hook(ON_START, priority = 0)]
+fn greet<T>(_name: &str) {}
+
+fn link_hooks() {
+    link(flush::<Disk>);
+    link(flush::<Memory>);
+    link(close_connections::<u8>);
+    link(audit::<u8>);
+    link(goodbye::<u8>);
+    link(greet::<u8>);
+}
+
+#[test]
+fn priority_then_name() {
+    let ctx = Ctx { log: Mutex::new(Vec::new()) };
+    let report = hooks::run(&ON_SHUTDOWN, &ctx);
+    let log = ctx.log.into_inner().unwrap();
+    assert_eq!(log, ["close", "audit", "flush disk", "flush memory", "goodbye"]);
+
+    let order: Vec<_> = report.ran.iter().map(|entry| (entry.pos(), entry.name().unwrap())).collect();
+    assert_eq!(
+        order,
+        [(Some(5), "close"), (Some(10), "audit"), (Some(10), "flush"), (Some(10), "flush"), (None, "goodbye")],
+    );
+    assert_eq!(report.ran[2].type_args(), Some("hooks::Disk"));
+    assert_eq!(report.ran[3].type_args(), Some("hooks::Memory"));
+    assert!(!report.is_ok());
+    assert_eq!(report.failures.len(), 2);
+    assert_eq!(report.failures[0].entry.name(), Some("close"));
+    match &report.failures[0].error {
+        HookError::Failed(error) => assert_eq!(error, "u8 still open"),
+        HookError::Panicked(_) => panic!("expected an error"),
+    }
+    assert_eq!(report.failures[1].entry.name(), Some("audit"));
+    assert_eq!(report.failures[1].error.to_string(), "panicked: audit log unavailable");
+    link_hooks();
+}
+
+#[test]
+fn unit_handlers() {
+    let report = hooks::run(&ON_START, "app");
+    assert!(report.is_ok());
+    assert_eq!(report.ran.len(), 1);
+    assert_eq!(report.ran[0].pos(), Some(0));
+    link_hooks();
+}
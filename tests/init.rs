@@ -0,0 +1,129 @@
+use generic_linkme::init::{self, InitError};
+use generic_linkme::{distributed_fn_slice, link};
+use std::any::type_name;
+use std::sync::Mutex;
+
+static LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn log(line: impl Into<String>) {
+    LOG.lock().unwrap().push(line.into());
+}
+
+#[distributed_fn_slice]
+pub static STARTUP: [fn()] = [..];
+
+#[distributed_fn_slice(STARTUP, name = "server", after = ["config"])]
+fn server() {
+    log("server");
+}
+
+#[distributed_fn_slice(STARTUP, name = "pool", after = ["config"], before = ["server"])]
+fn pool<T>() {
+    log(format!("pool {}", type_name::<T>()));
+}
+
+#[distributed_fn_slice(STARTUP, after = ["server"])]
+fn metrics() {
+    log("metrics");
+}
+
+#[distributed_fn_slice(STARTUP, name = "config")]
+fn config() {
+    log("config");
+}
+
+#[distributed_fn_slice(STARTUP, name = "banner", pos = 0)]
+fn banner() {
+    log("banner");
+}
+
+#[distributed_fn_slice]
+pub static CYCLIC: [fn()] = [..];
+
+#[distributed_fn_slice(CYCLIC, name = "a", after = ["c"])]
+fn a<T>() {}
+
+#[distributed_fn_slice(CYCLIC, name = "b", after = ["a"])]
+fn b<T>() {}
+
+#[distributed_fn_slice(CYCLIC, name = "c", before = ["a"], after = ["b"])]
+fn c<T>() {}
+
+#[distributed_fn_slice(CYCLIC, name = "d")]
+fn d<T>() {}
+
+#[distributed_fn_slice]
+pub static MISSING: [fn()] = [..];
+
+#[distributed_fn_slice(MISSING, name = "cache", after = ["storage"])]
+fn cache<T>() {}
+
+fn link_inits() {
+    link(server);
+    link(pool::<u8>);
+    link(pool::<u16>);
+    link(metrics);
+    link(config);
+    link(banner);
+    link(a::<u8>);
+    link(b::<u8>);
+    link(c::<u8>);
+    link(d::<u8>);
+    link(cache::<u8>);
+}
+
+#[test]
+fn ordered_by_relations_once() {
+    let order = init::run(&STARTUP).unwrap();
+    let log = LOG.lock().unwrap().clone();
+    assert_eq!(log[..2], ["banner", "config"]);
+    let mut pools = log[2..4].to_vec();
+    pools.sort();
+    assert_eq!(pools, ["pool u16", "pool u8"]);
+    assert_eq!(log[4..], ["server", "metrics"]);
+
+    let names: Vec<_> = order.iter().map(|entry| entry.name()).collect();
+    assert_eq!(
+        names,
+        [Some("banner"), Some("config"), Some("pool"), Some("pool"), Some("server"), None],
+    );
+    let metrics = order.last().unwrap();
+    assert_eq!(metrics.after(), ["server"]);
+    assert!(metrics.before().is_empty());
+
+    // Running the slice again returns the same order without calling them.
+    assert_eq!(init::run(&STARTUP).unwrap(), order);
+    assert_eq!(LOG.lock().unwrap().len(), 6);
+    link_inits();
+}
+
+#[test]
+fn cycle() {
+    let err = init::run(&CYCLIC).unwrap_err();
+    let names: Vec<_> = match &err {
+        InitError::Cycle(entries) => entries.iter().map(|entry| entry.name().unwrap()).collect(),
+        _ => panic!("{}", err),
+    };
+    assert_eq!(names.len(), 3);
+    assert!(!names.contains(&"d"));
+    assert!(err.to_string().starts_with("cycle between init functions: "));
+    assert_eq!(init::run(&CYCLIC).unwrap_err(), err);
+    link_inits();
+}
+
+#[test]
+fn missing_dependency() {
+    let err = init::run(&MISSING).unwrap_err();
+    match &err {
+        InitError::Missing { entry, name } => {
+            assert_eq!(entry.name(), Some("cache"));
+            assert_eq!(*name, "storage");
+        }
+        _ => panic!("{}", err),
+    }
+    assert_eq!(
+        err.to_string(),
+        "init \"cache\" is ordered against \"storage\", which no element of the slice is named",
+    );
+    link_inits();
+}
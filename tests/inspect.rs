@@ -0,0 +1,40 @@
+#![cfg(all(feature = "symtab", feature = "disasm"))]
+
+use std::env;
+use generic_linkme::{distributed_fn_slice, link};
+use generic_linkme::inspect::inspect;
+
+#[distributed_fn_slice]
+pub static HANDLERS: [fn() -> usize] = [..];
+
+#[distributed_fn_slice(HANDLERS)]
+fn handler<T>() -> usize {
+    std::mem::size_of::<T>()
+}
+
+#[distributed_fn_slice(HANDLERS, 7)]
+fn keyed_handler<T>() -> usize {
+    std::mem::align_of::<T>()
+}
+
+fn link_elements() {
+    link(handler::<u16>);
+    link(keyed_handler::<u64>);
+}
+
+#[test]
+fn describes_own_executable() {
+    let report = inspect(&env::current_exe().unwrap()).unwrap();
+    let slice = report.split_once("HANDLERS\n").unwrap().1;
+    assert!(slice.starts_with("  declarations: 1 (ok)\n"));
+    assert!(slice.contains("  section generic_linkme_HANDLERS at "));
+    assert!(slice.contains(", sort key 0007\n"));
+    // Stripped executables, as with the strip-symbols profile, only have the
+    // addresses of the bodies and their targets.
+    if !slice.contains("    no symbols for the element bodies\n") {
+        assert!(slice.contains("::handler_generic_linkme_impl::handler_middle_impl"));
+        assert!(slice.contains("::keyed_handler_generic_linkme_impl::keyed_handler_middle_impl"));
+        assert!(slice.contains("::keyed_handler_generic_linkme_impl::keyed_handler_generic_linkme_meta, sort key 0007\n"));
+    }
+    link_elements();
+}
@@ -0,0 +1,56 @@
+#![cfg(all(feature = "link_dupcheck", target_os = "linux"))]
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// A library and a binary that each declare a slice named `HANDLERS`, built
+// with `link_dupcheck`, fail to link.
+#[test]
+fn duplicate_declaration_fails_to_link() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let fixture = manifest_dir.join("tests").join("link_dupcheck");
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("link_dupcheck");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            r#"[package]
+name = "dupcheck_fixture"
+version = "0.0.0"
+edition = "2021"
+publish = false
+
+[lib]
+path = {lib:?}
+
+[[bin]]
+name = "dupcheck_fixture"
+path = {main:?}
+
+[dependencies]
+generic_linkme = {{ path = {crate_dir:?}, default-features = false, features = ["link_dupcheck"] }}
+
+[workspace]
+"#,
+            lib = fixture.join("lib.rs"),
+            main = fixture.join("bin.rs"),
+            crate_dir = manifest_dir,
+        ),
+    )
+    .unwrap();
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{}", stderr);
+    assert!(stderr.contains("error: linking with"), "{}", stderr);
+    assert!(stderr.contains("generic_linkme_declared_HANDLERS"), "{}", stderr);
+}
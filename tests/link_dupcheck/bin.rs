@@ -0,0 +1,9 @@
+use generic_linkme::distributed_fn_slice;
+
+// Declares a slice with the same name as the library's.
+#[distributed_fn_slice]
+pub static HANDLERS: [fn()] = [..];
+
+fn main() {
+    println!("{} {}", HANDLERS.len(), dupcheck_fixture::handlers());
+}
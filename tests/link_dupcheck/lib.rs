@@ -0,0 +1,8 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static HANDLERS: [fn()] = [..];
+
+pub fn handlers() -> usize {
+    HANDLERS.len()
+}
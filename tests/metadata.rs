@@ -0,0 +1,90 @@
+use generic_linkme::{distributed_fn_slice, link, Extraction, Reason};
+
+#[distributed_fn_slice]
+pub static FORMATS: [fn() -> String] = [..];
+
+#[distributed_fn_slice]
+pub static TEXT_FORMATS: [fn() -> String] = [..];
+
+#[distributed_fn_slice(FORMATS, pos = 20, name = "json")]
+fn json<T>() -> String {
+    format!("json {}", std::mem::size_of::<T>())
+}
+
+#[distributed_fn_slice(slices = [FORMATS, TEXT_FORMATS], pos = 10, name = "yaml")]
+fn yaml<T>() -> String {
+    format!("yaml {}", std::mem::size_of::<T>())
+}
+
+#[distributed_fn_slice(FORMATS)]
+fn raw<T>() -> String {
+    format!("raw {}", std::mem::size_of::<T>())
+}
+
+#[distributed_fn_slice(FORMATS, when = any())]
+fn disabled<T>() -> String {
+    format!("disabled {}", std::mem::size_of::<T>())
+}
+
+fn link_elements() {
+    link(json::<u32>);
+    link(yaml::<u8>);
+    link(yaml::<u16>);
+    link(raw::<u64>);
+    link(disabled::<u8>);
+}
+
+#[test]
+fn ordered_by_pos() {
+    let v: Vec<String> = FORMATS.iter().map(|f| f()).collect();
+    assert_eq!(v.len(), 4);
+    assert!(v[..2].contains(&"yaml 1".to_owned()));
+    assert!(v[..2].contains(&"yaml 2".to_owned()));
+    assert_eq!(v[2..], ["json 4", "raw 8"]);
+    link_elements();
+}
+
+#[test]
+fn describe_reports_name_and_pos() {
+    let entries: Vec<_> = (0..FORMATS.len()).map(|i| FORMATS.describe(i).unwrap()).collect();
+    let names: Vec<_> = entries.iter().map(|entry| entry.name()).collect();
+    assert_eq!(names, [Some("yaml"), Some("yaml"), Some("json"), None]);
+    let positions: Vec<_> = entries.iter().map(|entry| entry.pos()).collect();
+    assert_eq!(positions, [Some(10), Some(10), Some(20), None]);
+    assert!(entries[2].to_string().ends_with(" (\"json\", pos 20)"));
+    let mut type_args: Vec<_> = entries.iter().map(|entry| entry.type_args()).collect();
+    type_args[..2].sort();
+    assert_eq!(type_args, [Some("u16"), Some("u8"), Some("u32"), None]);
+    link_elements();
+}
+
+#[test]
+fn added_to_every_listed_slice() {
+    let mut v: Vec<String> = TEXT_FORMATS.iter().map(|f| f()).collect();
+    v.sort();
+    assert_eq!(v, ["yaml 1", "yaml 2"]);
+    assert_eq!(TEXT_FORMATS.describe(0).unwrap().name(), Some("yaml"));
+    link_elements();
+}
+
+#[test]
+fn disabled_element_is_callable() {
+    assert_eq!(disabled::<u8>(), "disabled 1");
+    link_elements();
+}
+
+#[test]
+fn extractions_agree() {
+    let Some(disassembly) = FORMATS.addresses(Extraction::Disassembly) else { return };
+    for extraction in [Extraction::Symbols, Extraction::Table] {
+        if let Some(addresses) = FORMATS.addresses(extraction) {
+            assert_eq!(addresses, disassembly);
+        }
+    }
+    let report = FORMATS.report().unwrap();
+    assert_eq!(report.bodies.iter().filter(|body| body.reason == Reason::Metadata).count(), 2);
+    assert_eq!(report.bodies.iter().filter(|body| body.reason == Reason::Key).count(), 3);
+    assert!(FORMATS.debug_string().contains("<- metadata"));
+    assert!(FORMATS.debug_string().contains("<- key"));
+    link_elements();
+}
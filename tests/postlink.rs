@@ -0,0 +1,55 @@
+#![cfg(all(feature = "symtab", feature = "disasm", feature = "postlink"))]
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+use generic_linkme::{distributed_fn_slice, link, Extraction};
+use generic_linkme::postlink;
+
+#[distributed_fn_slice]
+pub static SIZES: [fn() -> usize] = [..];
+
+#[distributed_fn_slice(SIZES)]
+fn size_of<T>() -> usize {
+    std::mem::size_of::<T>()
+}
+
+fn link_elements() {
+    link(size_of::<u8>);
+    link(size_of::<u32>);
+    link(size_of::<[u64; 3]>);
+}
+
+#[test]
+fn table_agrees_with_disassembly() {
+    let disassembly = SIZES.addresses(Extraction::Disassembly).unwrap();
+    match SIZES.addresses(Extraction::Table) {
+        Some(table) => assert_eq!(table, disassembly),
+        None => assert!(env::var_os("GENERIC_LINKME_EXPECT_TABLE").is_none()),
+    }
+
+    let mut v: Vec<usize> = SIZES.iter().map(|f| f()).collect();
+    v.sort();
+    assert_eq!(v, [1, 4, 24]);
+    link_elements();
+}
+
+#[test]
+fn patched_executable_reads_table() {
+    let exe = env::current_exe().unwrap();
+    let copy = env::temp_dir().join(format!("generic-linkme-postlink-{}", process::id()));
+    fs::copy(&exe, &copy).unwrap();
+
+    let resolved = postlink::patch(&copy).unwrap();
+    let sizes = resolved.iter().find(|table| table.name == "SIZES").unwrap();
+    assert_eq!(sizes.offsets.len(), 3);
+    assert_eq!(postlink::patch(&copy).unwrap(), resolved);
+
+    let status = Command::new(&copy)
+        .args(["--exact", "table_agrees_with_disassembly"])
+        .env("GENERIC_LINKME_EXPECT_TABLE", "1")
+        .status()
+        .unwrap();
+    fs::remove_file(&copy).unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,80 @@
+use generic_linkme::{distributed_fn_slice, link, Extraction, Reason};
+
+#[distributed_fn_slice]
+pub static ARRAYS: [fn([u64; 64]) -> u64] = [..];
+
+#[distributed_fn_slice(ARRAYS)]
+fn sum<T>(array: [u64; 64]) -> u64 {
+    array.iter().sum::<u64>() + std::mem::size_of::<T>() as u64
+}
+
+#[distributed_fn_slice]
+pub static NAMED: [fn([u64; 64]) -> u64] = [..];
+
+#[distributed_fn_slice(NAMED, pos = 1, name = "max")]
+fn max<T>(array: [u64; 64]) -> u64 {
+    array.iter().max().unwrap() + std::mem::size_of::<T>() as u64
+}
+
+fn link_elements() {
+    link(sum::<u8>);
+    link(sum::<u32>);
+    link(max::<u8>);
+    link(max::<u32>);
+}
+
+#[test]
+fn report_agrees_with_extraction() {
+    let Some(report) = ARRAYS.report() else { return };
+    assert_eq!(report.name, "ARRAYS");
+    assert_eq!(Some(report.targets()), ARRAYS.addresses(Extraction::Disassembly));
+    for body in &report.bodies {
+        assert_ne!(body.reason, Reason::NoCandidates);
+        if let Some(chosen) = body.chosen {
+            assert!(body.instructions.iter().any(|insn| insn.offset == body.candidates[chosen].offset));
+        }
+    }
+    assert!(ARRAYS.debug_string().contains("<- element"));
+
+    let mut v: Vec<u64> = ARRAYS.iter().map(|f| f([1; 64])).collect();
+    v.sort();
+    assert_eq!(v, [65, 68]);
+    link_elements();
+}
+
+#[test]
+fn report_finds_metadata_and_keys() {
+    let Some(report) = NAMED.report() else { return };
+    let starts = |reason| -> Vec<usize> {
+        report.bodies.iter()
+            .filter(|body| body.reason == reason)
+            .map(|body| report.address + body.offset)
+            .collect()
+    };
+    let metadata_fns = starts(Reason::Metadata);
+    let key_fns = starts(Reason::Key);
+    assert_eq!(metadata_fns.len(), 1);
+    assert_eq!(key_fns.len(), 2);
+    let elements: Vec<_> = report.bodies.iter().filter(|body| body.chosen.is_some()).collect();
+    assert_eq!(elements.len(), 2);
+    for body in elements {
+        assert!(matches!(body.metadata, Some(i) if metadata_fns.contains(&body.candidates[i].target)));
+        assert!(matches!(body.key, Some(i) if key_fns.contains(&body.candidates[i].target)));
+    }
+    assert_eq!(Some(report.targets()), NAMED.addresses(Extraction::Disassembly));
+    assert!(NAMED.debug_string().contains("<- metadata"));
+    assert!(NAMED.debug_string().contains("<- key"));
+
+    let mut v: Vec<u64> = NAMED.iter().map(|f| f([1; 64])).collect();
+    v.sort();
+    assert_eq!(v, [2, 5]);
+    link_elements();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn report_round_trips_through_json() {
+    let Some(report) = ARRAYS.report() else { return };
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(serde_json::from_str::<generic_linkme::DisassemblyReport>(&json).unwrap(), report);
+}
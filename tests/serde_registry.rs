@@ -0,0 +1,95 @@
+#![cfg(feature = "serde")]
+
+use generic_linkme::serde_registry;
+use generic_linkme::serde_registry::tags;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[serde_registry]
+pub trait Message {
+    fn describe(&self) -> String;
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Envelope<T> {
+    id: u32,
+    payload: T,
+}
+
+#[serde_registry]
+impl<T: Serialize + DeserializeOwned + std::fmt::Debug + 'static> Message for Envelope<T> {
+    fn describe(&self) -> String {
+        format!("envelope {} {:?}", self.id, self.payload)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Ping {
+    Once,
+    Times(u8),
+    Until { deadline: Option<u64> },
+}
+
+#[serde_registry]
+impl Message for Ping {
+    fn describe(&self) -> String {
+        match self {
+            Ping::Once => "ping once".to_owned(),
+            Ping::Times(n) => format!("ping {} times", n),
+            Ping::Until { deadline } => format!("ping until {:?}", deadline),
+        }
+    }
+}
+
+fn messages() -> Vec<Box<dyn Message>> {
+    vec![
+        Box::new(Envelope { id: 1, payload: 7u8 }),
+        Box::new(Envelope { id: 2, payload: vec!["a".to_owned(), "b".to_owned()] }),
+        Box::new(Ping::Times(3)),
+        Box::new(Ping::Until { deadline: None }),
+    ]
+}
+
+#[test]
+fn round_trip() {
+    for message in messages() {
+        let json = serde_json::to_string(&message).unwrap();
+        let back: Box<dyn Message> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.describe(), message.describe());
+    }
+}
+
+#[test]
+fn tagged_with_type_name() {
+    let message: Box<dyn Message> = Box::new(Envelope { id: 1, payload: 7u8 });
+    let json = serde_json::to_string(&message).unwrap();
+    assert_eq!(json, r#"{"serde_registry::Envelope<u8>":{"id":1,"payload":7}}"#);
+    let once: Box<dyn Message> = serde_json::from_str(r#"{"serde_registry::Ping":"Once"}"#).unwrap();
+    assert_eq!(once.describe(), "ping once");
+}
+
+#[test]
+fn registered_instantiations() {
+    messages();
+    assert_eq!(
+        tags::<dyn Message>(),
+        [
+            "serde_registry::Envelope<alloc::vec::Vec<alloc::string::String>>",
+            "serde_registry::Envelope<u8>",
+            "serde_registry::Ping",
+        ],
+    );
+}
+
+#[test]
+fn unknown_tag() {
+    let json = r#"{"serde_registry::Envelope<u16>":{"id":1,"payload":7}}"#;
+    let err = serde_json::from_str::<Box<dyn Message>>(json).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "unknown Message type tag `serde_registry::Envelope<u16>`, expected one of \
+         `serde_registry::Envelope<alloc::vec::Vec<alloc::string::String>>`, \
+         `serde_registry::Envelope<u8>`, `serde_registry::Ping` at line 1 column 32",
+    );
+    messages();
+}
@@ -0,0 +1,95 @@
+use generic_linkme::link;
+use std::mem::size_of;
+
+mod decl {
+    use generic_linkme::distributed_fn_slice;
+
+    #[distributed_fn_slice]
+    pub static SIZES: [fn() -> usize] = [..];
+
+    #[distributed_fn_slice]
+    pub static LABELS: [fn(&str) -> String] = [..];
+
+    pub trait Shape {
+        fn sides(&self) -> usize;
+    }
+
+    #[distributed_fn_slice]
+    pub static SHAPES: [&'static dyn Shape] = [..];
+
+    #[distributed_fn_slice]
+    pub static SYNC_SHAPES: [&'static (dyn Shape + Send + Sync)] = [..];
+}
+
+mod imported {
+    use super::decl::SIZES;
+    use generic_linkme::distributed_fn_slice;
+    use std::mem::size_of;
+
+    #[distributed_fn_slice(SIZES)]
+    pub fn size<T>() -> usize {
+        size_of::<T>()
+    }
+}
+
+mod renamed {
+    use super::decl::LABELS as RENAMED;
+    use generic_linkme::distributed_fn_slice;
+    use std::any::type_name;
+
+    #[distributed_fn_slice(RENAMED)]
+    pub fn label<T>(prefix: &str) -> String {
+        format!("{} {}", prefix, type_name::<T>())
+    }
+}
+
+mod objects {
+    use super::decl::{Shape, SHAPES, SYNC_SHAPES as RENAMED};
+    use generic_linkme::distributed_fn_slice;
+    use std::marker::PhantomData;
+
+    pub struct Polygon<T>(pub PhantomData<T>);
+
+    #[distributed_fn_slice(SHAPES)]
+    impl<T: 'static> Shape for Polygon<T> {
+        fn sides(&self) -> usize {
+            std::mem::size_of::<T>()
+        }
+    }
+
+    pub struct Square;
+
+    #[distributed_fn_slice(RENAMED)]
+    impl Shape for Square {
+        fn sides(&self) -> usize {
+            4
+        }
+    }
+}
+
+#[test]
+fn imported_slice() {
+    let mut sizes: Vec<usize> = decl::SIZES.iter().map(|size| size()).collect();
+    sizes.sort_unstable();
+    assert_eq!(sizes, [size_of::<u8>(), size_of::<u64>()]);
+    link(imported::size::<u8>);
+    link(imported::size::<u64>);
+}
+
+#[test]
+fn renamed_slice() {
+    let labels: Vec<String> = decl::LABELS.iter().map(|label| label("a")).collect();
+    assert_eq!(labels, ["a u16"]);
+    link(renamed::label::<u16>);
+}
+
+#[test]
+fn imported_and_renamed_object_slices() {
+    use decl::Shape;
+    let sides: Vec<usize> = decl::SHAPES.iter().map(|shape| shape.sides()).collect();
+    assert_eq!(sides, [3]);
+    let sides: Vec<usize> = decl::SYNC_SHAPES.iter().map(|shape| shape.sides()).collect();
+    assert_eq!(sides, [4]);
+    assert_eq!(objects::Polygon::<[u8; 3]>(std::marker::PhantomData).sides(), 3);
+    assert_eq!(objects::Square.sides(), 4);
+}
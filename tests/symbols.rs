@@ -0,0 +1,62 @@
+#![cfg(feature = "symtab")]
+
+use std::any::type_name;
+use generic_linkme::{distributed_fn_slice, link, Extraction};
+
+#[distributed_fn_slice]
+pub static NAMES: [fn() -> String] = [..];
+
+#[distributed_fn_slice(NAMES)]
+fn name_1<T: ?Sized>() -> String {
+    type_name::<T>().to_string()
+}
+
+#[distributed_fn_slice(NAMES)]
+fn name_2<T, U>() -> String {
+    format!("{}, {}", type_name::<T>(), type_name::<U>())
+}
+
+fn link_elements() {
+    link(name_1::<str>);
+    link(name_1::<u8>);
+    link(name_2::<u32, u64>);
+    link(name_2::<fn(u32) -> u32, ()>);
+}
+
+#[test]
+fn symbols_agree_with_disassembly() {
+    let disassembly = NAMES.addresses(Extraction::Disassembly).unwrap();
+    // `None` in builds without symbols, e.g. the strip-symbols profile.
+    if let Some(symbols) = NAMES.addresses(Extraction::Symbols) {
+        assert_eq!(symbols, disassembly);
+    }
+
+    let mut v: Vec<String> = NAMES.iter().map(|f| f()).collect();
+    let mut e = vec![
+        type_name::<str>().to_string(),
+        type_name::<u8>().to_string(),
+        format!("{}, {}", type_name::<u32>(), type_name::<u64>()),
+        format!("{}, {}", type_name::<fn(u32) -> u32>(), type_name::<()>()),
+    ];
+    v.sort(); e.sort();
+    assert_eq!(v, e);
+    link_elements();
+}
+
+#[test]
+fn describe_names_middle_impls() {
+    for (index, f) in NAMES.iter().enumerate() {
+        let entry = NAMES.describe(index).unwrap();
+        assert_eq!(entry.index(), index);
+        assert_eq!(entry.address(), *f as usize);
+        // `None` in builds without symbols, e.g. the strip-symbols profile.
+        if let Some(symbol) = entry.symbol() {
+            assert!(symbol.contains("_middle_impl"), "{}", symbol);
+            assert!(entry.to_string().ends_with(symbol));
+        } else {
+            assert!(entry.to_string().ends_with(" unknown"));
+        }
+    }
+    assert_eq!(NAMES.describe(NAMES.len()), None);
+    link_elements();
+}
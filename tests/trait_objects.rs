@@ -0,0 +1,71 @@
+use generic_linkme::distributed_fn_slice;
+use std::any::type_name;
+use std::marker::PhantomData;
+
+pub trait Codec {
+    fn name(&self) -> String;
+}
+
+#[distributed_fn_slice]
+pub static CODECS: [&'static dyn Codec] = [..];
+
+pub struct JsonCodec<T>(PhantomData<T>);
+
+#[distributed_fn_slice(CODECS)]
+impl<T: 'static> Codec for JsonCodec<T> {
+    fn name(&self) -> String {
+        format!("json {}", type_name::<T>())
+    }
+}
+
+pub struct RawCodec;
+
+#[distributed_fn_slice(CODECS, pos = 0)]
+impl Codec for RawCodec {
+    fn name(&self) -> String {
+        "raw".to_owned()
+    }
+}
+
+pub struct Versioned {
+    version: u32,
+}
+
+#[distributed_fn_slice(CODECS, value = Versioned { version: 2 })]
+impl Codec for Versioned {
+    fn name(&self) -> String {
+        format!("versioned {}", self.version)
+    }
+}
+
+fn use_codecs() {
+    let codecs: [&dyn Codec; 4] = [
+        &JsonCodec::<u8>(PhantomData),
+        &JsonCodec::<String>(PhantomData),
+        &RawCodec,
+        &Versioned { version: 1 },
+    ];
+    for codec in codecs {
+        codec.name();
+    }
+}
+
+#[test]
+fn one_object_per_instantiation() {
+    let mut names: Vec<String> = CODECS.iter().map(|codec| codec.name()).collect();
+    assert_eq!(names.len(), 4);
+    assert_eq!(names[0], "raw");
+    names.sort();
+    assert_eq!(names, ["json alloc::string::String", "json u8", "raw", "versioned 2"]);
+    use_codecs();
+}
+
+#[test]
+fn objects_are_cached() {
+    let first = CODECS.static_slice();
+    let second = CODECS.static_slice();
+    assert_eq!(first.as_ptr(), second.as_ptr());
+    let describe = CODECS.describe(0).unwrap();
+    assert_eq!(describe.pos(), Some(0));
+    use_codecs();
+}
@@ -0,0 +1,46 @@
+use generic_linkme::{link, register_type, TypeInfo, TYPES};
+use std::any::TypeId;
+
+pub struct Plugin {
+    pub name: String,
+    pub version: u32,
+}
+
+pub struct Marker;
+
+fn use_types() {
+    let info = register_type::<Plugin>();
+    assert_eq!(info.name, "types::Plugin");
+    link(register_type::<Marker>);
+    link(register_type::<[u16; 3]>);
+}
+
+#[test]
+fn lookup_by_id() {
+    let info = TypeInfo::by_id(TypeId::of::<Plugin>()).unwrap();
+    assert_eq!(info.name, "types::Plugin");
+    assert_eq!(info.size, std::mem::size_of::<Plugin>());
+    assert_eq!(info.align, std::mem::align_of::<Plugin>());
+    assert!(info.needs_drop);
+    assert!(TypeInfo::by_id(TypeId::of::<i128>()).is_none());
+    use_types();
+}
+
+#[test]
+fn lookup_by_name() {
+    let info = TypeInfo::by_name("[u16; 3]").unwrap();
+    assert_eq!((info.size, info.align, info.needs_drop), (6, 2, false));
+    let marker = TypeInfo::by_name("types::Marker").unwrap();
+    assert_eq!(marker.type_id, TypeId::of::<Marker>());
+    assert_eq!(marker.size, 0);
+    assert!(TypeInfo::by_name("types::Missing").is_none());
+    use_types();
+}
+
+#[test]
+fn all_types() {
+    let names: Vec<&str> = TypeInfo::all().iter().map(|info| info.name).collect();
+    assert_eq!(names, ["[u16; 3]", "types::Marker", "types::Plugin"]);
+    assert_eq!(TYPES.len(), 3);
+    use_types();
+}
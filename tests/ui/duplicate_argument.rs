@@ -0,0 +1,21 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn() -> u32] = [..];
+
+#[distributed_fn_slice(SLICE, name = "a", name = "b")]
+fn element<T>() -> u32 {
+    0
+}
+
+#[distributed_fn_slice(SLICE, after = ["a"], after = ["b"])]
+fn after<T>() -> u32 {
+    0
+}
+
+#[distributed_fn_slice(SLICE, before = ["a"], before = ["b"])]
+fn before<T>() -> u32 {
+    0
+}
+
+fn main() {}
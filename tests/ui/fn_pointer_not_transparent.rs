@@ -0,0 +1,6 @@
+use generic_linkme::FnPointer;
+
+#[derive(Clone, Copy, FnPointer)]
+pub struct Handler(fn(u32) -> u32);
+
+fn main() {}
@@ -0,0 +1,17 @@
+use generic_linkme::distributed_fn_slice;
+
+pub trait Named {
+    fn name(&self) -> &'static str {
+        "unnamed"
+    }
+}
+
+#[distributed_fn_slice]
+pub static NAMED: [&'static dyn Named] = [..];
+
+pub struct Anonymous;
+
+#[distributed_fn_slice(NAMED)]
+impl Named for Anonymous {}
+
+fn main() {}
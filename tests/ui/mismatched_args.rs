@@ -0,0 +1,11 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn(u32) -> u32] = [..];
+
+#[distributed_fn_slice(SLICE)]
+fn element<T>(x: u64) -> u32 {
+    x as u32
+}
+
+fn main() {}
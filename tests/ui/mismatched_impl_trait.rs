@@ -0,0 +1,13 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn(u32) -> String] = [..];
+
+#[distributed_fn_slice(SLICE)]
+fn element(values: impl Iterator<Item = u8>) -> String {
+    values.count().to_string()
+}
+
+fn main() {
+    element([1u8].into_iter());
+}
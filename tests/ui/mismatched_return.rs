@@ -0,0 +1,11 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn(&str) -> usize] = [..];
+
+#[distributed_fn_slice(SLICE)]
+fn element<T>(s: &str) -> String {
+    s.to_owned()
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn()] = [..];
+
+#[distributed_fn_slice(SLICE)]
+unsafe fn element<T>() {}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use generic_linkme::di::Provider;
+use generic_linkme::{distributed_fn_slice, provider};
+
+#[distributed_fn_slice]
+pub static PROVIDERS: [fn() -> Provider] = [..];
+
+#[provider(PROVIDERS)]
+fn provide<K: Default + 'static, V: Default + 'static>() -> Box<dyn std::any::Any> {
+    Box::new((K::default(), V::default()))
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use generic_linkme::distributed_fn_slice;
+
+#[distributed_fn_slice]
+pub static SLICE: [fn() -> u32] = [..];
+
+#[distributed_fn_slice(SLICE, pos = 1, priority = 2)]
+fn element<T>() -> u32 {
+    0
+}
+
+fn main() {}
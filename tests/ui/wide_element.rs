@@ -0,0 +1,14 @@
+use generic_linkme::{distributed_fn_slice, FnPointer};
+
+#[derive(Clone, Copy)]
+pub struct Wide(fn(), fn());
+
+unsafe impl FnPointer for Wide {
+    type Trampoline = Wide;
+    type Signature = fn();
+}
+
+#[distributed_fn_slice]
+pub static SLICE: [Wide] = [..];
+
+fn main() {}
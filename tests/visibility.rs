@@ -0,0 +1,56 @@
+#![deny(private_interfaces, unreachable_pub)]
+
+use generic_linkme::{distributed_fn_slice, link};
+
+pub(crate) struct Request {
+    pub(crate) path: &'static str,
+}
+
+#[distributed_fn_slice]
+pub(crate) static ROUTES: [fn(&Request) -> String] = [..];
+
+mod outer {
+    pub(crate) mod inner {
+        use super::super::Request;
+        use generic_linkme::distributed_fn_slice;
+        use std::mem::size_of;
+
+        #[distributed_fn_slice(crate::ROUTES)]
+        fn private_route<T>(request: &Request) -> String {
+            format!("private {} {}", request.path, size_of::<T>())
+        }
+
+        #[distributed_fn_slice(crate::ROUTES)]
+        pub(crate) fn crate_route<T>(request: &Request) -> String {
+            format!("crate {} {}", request.path, size_of::<T>())
+        }
+
+        #[distributed_fn_slice(crate::ROUTES)]
+        pub(super) fn super_route<T>(request: &Request) -> String {
+            format!("super {} {}", request.path, size_of::<T>())
+        }
+
+        // Would have clashed with the helper generated for `private_route`.
+        #[allow(dead_code)]
+        fn private_route_generic_linkme_impl() {}
+
+        pub(crate) fn link_private() {
+            generic_linkme::link(private_route::<u8>);
+        }
+    }
+
+    pub(crate) fn link_super() {
+        generic_linkme::link(inner::super_route::<u8>);
+    }
+}
+
+#[test]
+fn visibility_is_kept() {
+    let request = Request { path: "/" };
+    let mut v: Vec<String> = ROUTES.iter().map(|route| route(&request)).collect();
+    v.sort();
+    assert_eq!(v, ["crate / 1", "private / 1", "super / 1"]);
+    outer::inner::link_private();
+    outer::link_super();
+    link(outer::inner::crate_route::<u8>);
+}